@@ -0,0 +1,252 @@
+use std::borrow::Borrow;
+use std::convert::Infallible;
+use std::future::Future;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+
+use crate::flight::InFlight;
+use crate::{CacheStats, CleanupMode, DefaultHashBuilder, LruCache};
+
+/// Кеш для асинхронного кода: [`LruCache`] под `tokio::sync::Mutex`
+///
+/// Ожидание блокировки приостанавливает задачу, а не поток исполнителя, поэтому
+/// блокировку можно держать через `.await`. Копии (`clone`) указывают на один кеш.
+pub struct AsyncLruCache<K, V, S = DefaultHashBuilder> {
+    inner: Arc<Mutex<LruCache<K, V, S>>>,
+    // загрузки [`AsyncLruCache::get_with`], которые ещё не завершились
+    loading: Arc<InFlight<K, V>>,
+}
+
+impl<K, V, S> Clone for AsyncLruCache<K, V, S> {
+    fn clone(&self) -> Self {
+        AsyncLruCache {
+            inner: self.inner.clone(),
+            loading: self.loading.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> AsyncLruCache<K, V> {
+    pub fn new(capacity: usize, cleanup_mode: CleanupMode) -> Self {
+        LruCache::new(capacity, cleanup_mode).into()
+    }
+}
+
+impl<K, V, S> From<LruCache<K, V, S>> for AsyncLruCache<K, V, S> {
+    fn from(cache: LruCache<K, V, S>) -> Self {
+        AsyncLruCache {
+            inner: Arc::new(Mutex::new(cache)),
+            loading: Arc::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> AsyncLruCache<K, V, S> {
+    /// Блокирует кеш для нескольких операций подряд или методов, которых нет у обёртки
+    pub async fn lock(&self) -> MutexGuard<'_, LruCache<K, V, S>> {
+        self.inner.lock().await
+    }
+
+    pub async fn insert(&self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        self.lock().await.put(key, value, ttl)
+    }
+
+    /// Копия значения с отметкой элемента как недавно использованного
+    pub async fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.lock().await.get(key).cloned()
+    }
+
+    /// Возвращает элемент, а при его отсутствии дожидается `f` и сохраняет результат
+    ///
+    /// `f` выполняется без блокировки, поэтому другие задачи продолжают работать с кешем;
+    /// если за это время ключ успели добавить, возвращается уже сохранённое значение.
+    pub async fn get_or_insert_with<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+        V: Clone,
+    {
+        if let Some(value) = self.get(&key).await {
+            return value;
+        }
+
+        let value = f().await;
+        let mut cache = self.lock().await;
+        // промах уже учтён первым чтением
+        if let Some(current) = cache.peek(&key) {
+            return current.clone();
+        }
+        cache.put(key, value.clone(), None);
+        value
+    }
+
+    /// Возвращает элемент, а при его отсутствии загружает через `f`; одновременные
+    /// вызовы для одного ключа дожидаются единственной загрузки и получают её результат
+    ///
+    /// Если задачу, выполняющую загрузку, отменили или `f` запаниковало, загрузку
+    /// повторяет следующая ожидающая задача со своим `f`.
+    pub async fn get_with<F, Fut>(&self, key: K, f: F) -> V
+    where
+        K: Clone,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+        V: Clone,
+    {
+        let result = self
+            .try_get_with(key, || async { Ok::<_, Infallible>(f().await) })
+            .await;
+        match result {
+            Ok(value) => value,
+        }
+    }
+
+    /// Вариант `get_with` для загрузки с ошибкой: ошибка возвращается загрузившей
+    /// задаче, а ожидавшие задачи повторяют загрузку сами
+    pub async fn try_get_with<F, Fut, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        K: Clone,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        V: Clone,
+    {
+        if let Some(value) = self.get(&key).await {
+            return Ok(value);
+        }
+
+        self.loading
+            .try_run_async(&key, || async {
+                // предыдущая загрузка могла завершиться между промахом и регистрацией
+                if let Some(value) = self.lock().await.peek(&key) {
+                    return Ok(value.clone());
+                }
+                let value = f().await?;
+                self.lock().await.put(key.clone(), value.clone(), None);
+                Ok(value)
+            })
+            .await
+    }
+
+    pub async fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().await.remove(key)
+    }
+
+    pub async fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().await.contains_key(key)
+    }
+
+    pub async fn clear(&self) {
+        self.lock().await.clear()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.lock().await.is_empty()
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        self.lock().await.stats()
+    }
+}
+
+impl<K, V, S> AsyncLruCache<K, V, S>
+where
+    K: Eq + Hash + Send + 'static,
+    V: Send + 'static,
+    S: BuildHasher + Send + 'static,
+{
+    /// Фоновая задача очистки, см. [`LruCache::spawn_maintenance`]
+    pub fn spawn_maintenance(&self, interval: Duration) -> JoinHandle<()> {
+        LruCache::spawn_maintenance(&self.inner, interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_async_cache() {
+        let cache = AsyncLruCache::new(2, CleanupMode::OnAccess);
+        assert_eq!(cache.insert("a", 1, None).await, None);
+        assert_eq!(cache.get(&"a").await, Some(1));
+
+        let value = cache
+            .get_or_insert_with("b", || async {
+                tokio::task::yield_now().await;
+                2
+            })
+            .await;
+        assert_eq!(value, 2);
+        assert_eq!(cache.stats().await.misses, 1);
+        // значение уже есть, загрузка не выполняется
+        let value = cache.get_or_insert_with("b", || async { 3 }).await;
+        assert_eq!(value, 2);
+
+        // блокировка удерживается через `.await`
+        let handle = cache.clone();
+        let mut guard = cache.lock().await;
+        let task = tokio::spawn(async move { handle.remove(&"a").await });
+        tokio::task::yield_now().await;
+        guard.put("c", 3, None);
+        drop(guard);
+
+        assert_eq!(task.await.unwrap(), None);
+        assert_eq!(cache.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_with() {
+        static LOADS: AtomicU32 = AtomicU32::new(0);
+        let cache = AsyncLruCache::new(10, CleanupMode::OnAccess);
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_with("a", || async {
+                            LOADS.fetch_add(1, Ordering::Relaxed);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            1
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 1);
+        }
+
+        assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+
+        // отменённая загрузка не оставляет заглушку
+        let handle = cache.clone();
+        let task =
+            tokio::spawn(async move { handle.get_with("b", std::future::pending::<i32>).await });
+        tokio::task::yield_now().await;
+        task.abort();
+        let value = cache.try_get_with("b", || async { Ok::<_, ()>(2) }).await;
+        assert_eq!(value, Ok(2));
+    }
+}
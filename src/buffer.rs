@@ -0,0 +1,240 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::thread;
+
+use crate::hooks::{Borrowed, MissedKey};
+use crate::{KeyWrapper, LruCache, Node, RefreshMode};
+
+/// Сколько чтений копит одна полоса буфера до принудительного воспроизведения
+const STRIPE_LEN: usize = 64;
+
+/// Полоса буфера: узлы, прочитанные под разделяемой блокировкой
+type Stripe<K, V> = Mutex<Vec<NonNull<Node<K, V>>>>;
+
+/// Кеш под `RwLock` с буфером чтений (как в Caffeine)
+///
+/// Чтение берёт только разделяемую блокировку и записывает прочитанный узел в одну
+/// из полос буфера, а перенос в начало очереди LRU откладывается до ближайшей
+/// эксклюзивной блокировки. Буфер теряет записи: занятая или заполненная полоса
+/// пропускает чтение, поэтому порядок LRU при высокой нагрузке приблизителен.
+pub(crate) struct BufferedCache<K, V, S> {
+    cache: RwLock<LruCache<K, V, S>>,
+    // узлы освобождаются только под эксклюзивной блокировкой, которая первым делом
+    // опустошает буфер, поэтому указатели в нём всегда указывают на живые узлы
+    stripes: Box<[Stripe<K, V>]>,
+    misses: AtomicU64,
+    // эксклюзивные блокировки, которым пришлось ждать другой поток
+    contention: AtomicU64,
+}
+
+// указатели буфера разыменовываются только под эксклюзивной блокировкой кеша
+unsafe impl<K: Send, V: Send, S: Send> Send for BufferedCache<K, V, S> {}
+// под разделяемой блокировкой потоки одновременно читают ключи, значения и хешер
+unsafe impl<K: Send + Sync, V: Send + Sync, S: Send + Sync> Sync for BufferedCache<K, V, S> {}
+
+fn stripe_index(stripes: usize) -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        // полоса закрепляется за потоком, чтобы соседние потоки не делили одну полосу
+        static STRIPE: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    STRIPE.with(|id| *id) & (stripes - 1)
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> BufferedCache<K, V, S> {
+    pub(crate) fn new(cache: LruCache<K, V, S>) -> Self {
+        let stripes = thread::available_parallelism()
+            .map_or(1, usize::from)
+            .next_power_of_two();
+
+        BufferedCache {
+            cache: RwLock::new(cache),
+            stripes: (0..stripes).map(|_| Mutex::new(Vec::new())).collect(),
+            misses: AtomicU64::new(0),
+            contention: AtomicU64::new(0),
+        }
+    }
+
+    /// Чтение под разделяемой блокировкой; `f` получает непросроченное значение
+    pub(crate) fn read<Q, R>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&V) -> R,
+    ) -> Result<Option<R>, PoisonError<()>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read_counted(key, f, true)
+    }
+
+    /// Как [`BufferedCache::read`], но промах не учитывается: его учтёт
+    /// последующее чтение под эксклюзивной блокировкой
+    pub(crate) fn read_hit<Q, R>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&V) -> R,
+    ) -> Result<Option<R>, PoisonError<()>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read_counted(key, f, false)
+    }
+
+    fn read_counted<Q, R>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&V) -> R,
+        count_miss: bool,
+    ) -> Result<Option<R>, PoisonError<()>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let cache = self.shared()?;
+        let Some(node_ptr) = cache.lookup_shared(key) else {
+            if count_miss {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                cache.notify_miss(key);
+            }
+            return Ok(None);
+        };
+        let result = f(unsafe { &(*node_ptr.as_ptr()).value });
+
+        let stripe = &self.stripes[stripe_index(self.stripes.len())];
+        let full = match stripe.try_lock() {
+            Ok(mut reads) => {
+                if reads.len() < STRIPE_LEN {
+                    reads.push(node_ptr);
+                }
+                reads.len() == STRIPE_LEN
+            }
+            Err(_) => false,
+        };
+        drop(cache);
+
+        // заполненная полоса воспроизводится, только если кеш никем не занят
+        if full && let Ok(mut cache) = self.cache.try_write() {
+            self.replay(&mut cache);
+        }
+        Ok(Some(result))
+    }
+
+    /// Разделяемая блокировка для методов `&self`; буферизованные чтения в ней не видны
+    pub(crate) fn shared(&self) -> Result<RwLockReadGuard<'_, LruCache<K, V, S>>, PoisonError<()>> {
+        self.cache.read().map_err(|_| PoisonError::new(()))
+    }
+
+    /// Эксклюзивная блокировка с воспроизведением накопленных чтений
+    pub(crate) fn write(&self) -> Result<RwLockWriteGuard<'_, LruCache<K, V, S>>, PoisonError<()>> {
+        let mut cache = match self.cache.try_write() {
+            Ok(cache) => cache,
+            Err(TryLockError::WouldBlock) => {
+                self.contention.fetch_add(1, Ordering::Relaxed);
+                self.cache.write().map_err(|_| PoisonError::new(()))?
+            }
+            Err(TryLockError::Poisoned(_)) => return Err(PoisonError::new(())),
+        };
+        self.replay(&mut cache);
+        Ok(cache)
+    }
+
+    /// Число эксклюзивных блокировок, не взятых с первой попытки
+    pub(crate) fn contention(&self) -> u64 {
+        self.contention.load(Ordering::Relaxed)
+    }
+
+    fn replay(&self, cache: &mut LruCache<K, V, S>) {
+        let mut reads = Vec::new();
+        for stripe in &self.stripes {
+            reads.append(&mut stripe.lock().unwrap_or_else(PoisonError::into_inner));
+        }
+        cache.replay_reads(&reads, self.misses.swap(0, Ordering::Relaxed));
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Поиск для буферизованного чтения: кеш не изменяется, просроченный элемент
+    /// остаётся до ближайшей очистки
+    fn lookup_shared<Q>(&self, key: &Q) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = *self.map.get(KeyWrapper::from_ref(key))?;
+        unsafe { !(*node_ptr.as_ptr()).expired_at(self.now()) }.then_some(node_ptr)
+    }
+
+    /// Промах буферизованного чтения для [`AccessHooks`](crate::AccessHooks);
+    /// в статистику он попадает при воспроизведении
+    fn notify_miss<Q>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(hooks) = self.hooks.as_ref() {
+            hooks.on_miss(MissedKey::new(&Borrowed(key)));
+        }
+    }
+
+    /// Применяет отложенные чтения в порядке записи: попадания учитываются
+    /// в статистике и [`Expiry`](crate::Expiry), узлы переносятся в начало очереди
+    fn replay_reads(&mut self, reads: &[NonNull<Node<K, V>>], misses: u64) {
+        let now = self.now();
+        for _ in 0..misses {
+            self.stats.record_miss(now);
+        }
+
+        // обновление может вытеснить узлы, ещё не воспроизведённые из буфера,
+        // поэтому загрузка выполняется только после переноса всех узлов
+        for &node_ptr in reads {
+            self.record_hit(node_ptr, now);
+            self.queue_refresh(node_ptr, now);
+            self.on_read(node_ptr, now);
+            self.remove_node(node_ptr);
+            self.push_front(node_ptr);
+        }
+        if self
+            .refresh
+            .as_ref()
+            .is_some_and(|refresh| refresh.mode == RefreshMode::OnAccess)
+        {
+            self.run_pending_refreshes();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_buffered_reads() {
+        let buffered = BufferedCache::new(LruCache::new(3, CleanupMode::OnAccess));
+        {
+            let mut cache = buffered.write().unwrap();
+            cache.put("a", 1, None);
+            cache.put("b", 2, None);
+            cache.put("c", 3, None);
+        }
+
+        assert_eq!(buffered.read(&"a", |v| *v).unwrap(), Some(1));
+        assert_eq!(buffered.read(&"x", |v| *v).unwrap(), None);
+        // порядок под разделяемой блокировкой ещё прежний
+        assert_eq!(buffered.shared().unwrap().keys().next(), Some(&"c"));
+
+        let mut cache = buffered.write().unwrap();
+        assert_eq!(cache.keys().next(), Some(&"a"));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        cache.put("d", 4, None);
+        assert!(!cache.contains_key(&"b"));
+        assert!(cache.contains_key(&"a"));
+    }
+}
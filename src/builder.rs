@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::expiry::ExpiryQueue;
+use crate::refresh::RefreshAhead;
+use crate::stats::{self, Stats};
+use crate::{
+    AccessHooks, CleanupMode, Clock, DefaultHashBuilder, EarlyExpiration, Expiry, ExpiryIndex,
+    Loader, LruCache, RefreshMode, RemovalListener, Weigher,
+};
+
+/// Построитель [`LruCache`], позволяющий добавлять параметры без изменения `new()`
+///
+/// Без заданных ограничений создаётся неограниченный кеш, режим очистки по умолчанию
+/// [`CleanupMode::OnAccess`].
+pub struct LruCacheBuilder<K, V, S = DefaultHashBuilder> {
+    capacity: Option<usize>,
+    max_weight: Option<usize>,
+    cleanup_mode: CleanupMode,
+    default_ttl: Option<Duration>,
+    stale_window: Duration,
+    early_expiration: Option<EarlyExpiration>,
+    ttl_jitter: f64,
+    expiry_index: ExpiryIndex,
+    hasher: S,
+    weigher: Option<Arc<dyn Weigher<K, V> + Send + Sync>>,
+    clock: Option<Arc<dyn Clock + Send + Sync>>,
+    expiry: Option<Arc<dyn Expiry<K, V> + Send + Sync>>,
+    refresh: Option<RefreshAhead<K, V>>,
+    listener: Option<Box<dyn RemovalListener<K, V> + Send + Sync>>,
+    hooks: Option<Box<dyn AccessHooks<K> + Send + Sync>>,
+    stats_half_life: Duration,
+    negative_capacity: Option<usize>,
+    #[cfg(feature = "metrics")]
+    metrics_name: Option<String>,
+    marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> LruCacheBuilder<K, V> {
+    pub fn new() -> Self {
+        LruCacheBuilder {
+            capacity: None,
+            max_weight: None,
+            cleanup_mode: CleanupMode::OnAccess,
+            default_ttl: None,
+            stale_window: Duration::ZERO,
+            early_expiration: None,
+            ttl_jitter: 0.0,
+            expiry_index: ExpiryIndex::Ordered,
+            hasher: DefaultHashBuilder::default(),
+            weigher: None,
+            clock: None,
+            expiry: None,
+            refresh: None,
+            listener: None,
+            hooks: None,
+            stats_half_life: stats::DEFAULT_HALF_LIFE,
+            negative_capacity: None,
+            #[cfg(feature = "metrics")]
+            metrics_name: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Default for LruCacheBuilder<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> LruCacheBuilder<K, V, S> {
+    /// Максимальное число элементов; 0 отключает кеширование
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Максимальный суммарный вес элементов, см. [`LruCacheBuilder::weigher`]
+    pub fn max_weight(mut self, max_weight: usize) -> Self {
+        self.max_weight = Some(max_weight);
+        self
+    }
+
+    /// Функция веса элемента; без неё вес каждого элемента равен 1
+    pub fn weigher<W>(mut self, weigher: W) -> Self
+    where
+        W: Weigher<K, V> + Send + Sync + 'static,
+    {
+        self.weigher = Some(Arc::new(weigher));
+        self
+    }
+
+    pub fn cleanup_mode(mut self, cleanup_mode: CleanupMode) -> Self {
+        self.cleanup_mode = cleanup_mode;
+        self
+    }
+
+    /// Время жизни элементов, вставленных без явного `ttl`
+    pub fn default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    /// Окно, в течение которого просроченный элемент отдаётся [`LruCache::get_stale`] как устаревший
+    pub fn stale_window(mut self, stale_window: Duration) -> Self {
+        self.stale_window = stale_window;
+        self
+    }
+
+    /// Вероятностное досрочное истечение (XFetch) с ожидаемым временем пересчёта `delta`
+    pub fn early_expiration(mut self, delta: Duration, beta: f64) -> Self {
+        assert!(beta >= 0.0);
+        self.early_expiration = Some(EarlyExpiration { delta, beta });
+        self
+    }
+
+    /// Относительный разброс TTL, например 0.1 для ±10%
+    pub fn ttl_jitter(mut self, jitter: f64) -> Self {
+        assert!((0.0..=1.0).contains(&jitter));
+        self.ttl_jitter = jitter;
+        self
+    }
+
+    /// Источник времени вместо системных часов, например [`MockClock`](crate::MockClock) в тестах
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Политика времени жизни по ключу и значению вместо [`LruCacheBuilder::default_ttl`]
+    pub fn expiry<E>(mut self, expiry: E) -> Self
+    where
+        E: Expiry<K, V> + Send + Sync + 'static,
+    {
+        self.expiry = Some(Arc::new(expiry));
+        self
+    }
+
+    /// Упреждающее обновление: элемент, которому осталось жить меньше `threshold`,
+    /// при чтении перезагружается через `loader`, сразу или отложенно (см. [`RefreshMode`])
+    ///
+    /// Новое значение получает время жизни как при `put(.., None)`, поэтому нужен
+    /// [`LruCacheBuilder::default_ttl`] или [`LruCacheBuilder::expiry`]. Прежнее значение
+    /// получает слушатель с причиной [`RemovalCause::Replaced`](crate::RemovalCause::Replaced).
+    pub fn refresh_ahead<L>(mut self, threshold: Duration, mode: RefreshMode, loader: L) -> Self
+    where
+        K: Clone,
+        L: Loader<K, V> + Send + Sync + 'static,
+    {
+        self.refresh = Some(RefreshAhead {
+            threshold,
+            mode,
+            loader: Box::new(loader),
+            clone_key: K::clone,
+        });
+        self
+    }
+
+    /// Слушатель, получающий вытесненные, просроченные, заменённые и явно удалённые
+    /// элементы вместе с причиной, см. [`RemovalListener`]
+    pub fn removal_listener<L>(mut self, listener: L) -> Self
+    where
+        L: RemovalListener<K, V> + Send + Sync + 'static,
+    {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Наблюдение за вставками, попаданиями и промахами, см. [`AccessHooks`]
+    pub fn hooks<H>(mut self, hooks: H) -> Self
+    where
+        H: AccessHooks<K> + Send + Sync + 'static,
+    {
+        self.hooks = Some(Box::new(hooks));
+        self
+    }
+
+    /// Период полураспада для [`CacheStats::recent_hit_rate`](crate::CacheStats::recent_hit_rate),
+    /// по умолчанию минута
+    pub fn stats_half_life(mut self, half_life: Duration) -> Self {
+        assert!(!half_life.is_zero());
+        self.stats_half_life = half_life;
+        self
+    }
+
+    /// Максимальное число отметок [`LruCache::put_negative`], по умолчанию равно ёмкости
+    /// и меняется вместе с ней в [`LruCache::set_capacity`]; заданное здесь число не меняется
+    pub fn negative_capacity(mut self, capacity: usize) -> Self {
+        self.negative_capacity = Some(capacity);
+        self
+    }
+
+    /// Имя кеша для экспорта показателей, см. [`LruCache::publish_metrics`]
+    #[cfg(feature = "metrics")]
+    pub fn metrics_name(mut self, name: impl Into<String>) -> Self {
+        self.metrics_name = Some(name.into());
+        self
+    }
+
+    /// Структура индекса истечения, по умолчанию [`ExpiryIndex::Ordered`]
+    pub fn expiry_index(mut self, expiry_index: ExpiryIndex) -> Self {
+        self.expiry_index = expiry_index;
+        self
+    }
+
+    /// Пользовательский хешер вместо [`DefaultHashBuilder`]
+    pub fn hasher<S2>(self, hasher: S2) -> LruCacheBuilder<K, V, S2> {
+        LruCacheBuilder {
+            capacity: self.capacity,
+            max_weight: self.max_weight,
+            cleanup_mode: self.cleanup_mode,
+            default_ttl: self.default_ttl,
+            stale_window: self.stale_window,
+            early_expiration: self.early_expiration,
+            ttl_jitter: self.ttl_jitter,
+            expiry_index: self.expiry_index,
+            hasher,
+            weigher: self.weigher,
+            clock: self.clock,
+            expiry: self.expiry,
+            refresh: self.refresh,
+            listener: self.listener,
+            hooks: self.hooks,
+            stats_half_life: self.stats_half_life,
+            negative_capacity: self.negative_capacity,
+            #[cfg(feature = "metrics")]
+            metrics_name: self.metrics_name,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCacheBuilder<K, V, S> {
+    pub fn build(self) -> LruCache<K, V, S> {
+        let mut cache = if self.weigher.is_some() {
+            // нулевая ёмкость отключает кеш, как и без функции веса
+            let max_weight = match self.capacity {
+                Some(0) => 0,
+                _ => self.max_weight.unwrap_or(usize::MAX),
+            };
+            let map = HashMap::with_hasher(self.hasher);
+            let mut cache = LruCache::from_map(map, max_weight, self.cleanup_mode);
+            cache.weigher = self.weigher;
+            cache.max_entries = self.capacity;
+            cache
+        } else {
+            // без функции веса вес совпадает с числом элементов
+            let capacity = match (self.capacity, self.max_weight) {
+                (Some(capacity), Some(max_weight)) => capacity.min(max_weight),
+                (Some(limit), None) | (None, Some(limit)) => limit,
+                (None, None) => usize::MAX,
+            };
+
+            let map = if capacity == usize::MAX {
+                HashMap::with_hasher(self.hasher)
+            } else {
+                HashMap::with_capacity_and_hasher(capacity, self.hasher)
+            };
+            LruCache::from_map(map, capacity, self.cleanup_mode)
+        };
+
+        cache.default_ttl = self.default_ttl;
+        cache.stale_window = self.stale_window;
+        cache.early_expiration = self.early_expiration;
+        cache.ttl_jitter = self.ttl_jitter;
+        cache.expiry_policy = self.expiry;
+        cache.refresh = self.refresh;
+        cache.listener = self.listener;
+        cache.hooks = self.hooks;
+        if let Some(capacity) = self.negative_capacity {
+            cache.negative.capacity = capacity;
+            cache.negative.fixed = true;
+        }
+        #[cfg(feature = "metrics")]
+        {
+            cache.metrics_name = self.metrics_name;
+        }
+        if let Some(clock) = self.clock {
+            cache.clock = clock;
+        }
+        cache.expiry = ExpiryQueue::new(self.expiry_index, cache.now());
+        cache.stats = Stats::new(self.stats_half_life, cache.now());
+        cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::RandomState;
+
+    #[test]
+    fn test_builder_capacity() {
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .build();
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"c", &"b"]);
+    }
+
+    #[test]
+    fn test_builder_defaults_to_unbounded() {
+        let mut cache = LruCacheBuilder::default().build();
+        for i in 0..100 {
+            cache.put(i, i, None);
+        }
+
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.capacity(), usize::MAX);
+        assert!(
+            !LruCache::<i32, i32>::builder()
+                .capacity(0)
+                .build()
+                .contains_key(&0)
+        );
+    }
+
+    #[test]
+    fn test_builder_weigher_and_hasher() {
+        let mut cache = LruCache::builder()
+            .capacity(3)
+            .weigher(|_: &u32, v: &String| v.len() as u32)
+            .max_weight(10)
+            .hasher(RandomState::new())
+            .build();
+
+        cache.put(1, "aaaa".to_string(), None);
+        cache.put(2, "bbbb".to_string(), None);
+        cache.put(3, "cccc".to_string(), None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&3, &2]);
+
+        cache.put(4, "d".to_string(), None);
+        cache.put(5, "e".to_string(), None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&5, &4, &3]);
+        assert_eq!(cache.max_entries(), Some(3));
+    }
+
+    #[test]
+    fn test_builder_default_ttl() {
+        let cache: LruCache<&str, i32> = LruCache::builder()
+            .capacity(2)
+            .default_ttl(Duration::from_secs(1))
+            .build();
+
+        assert_eq!(cache.default_ttl(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_builder_zero_capacity_with_weigher() {
+        let mut cache = LruCache::builder()
+            .capacity(0)
+            .weigher(|_: &&str, v: &Vec<u8>| v.len() as u32)
+            .build();
+        assert!(cache.is_disabled());
+        cache.put("a", vec![1], None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_builder_cleanup_mode() {
+        let mut cache = LruCache::builder()
+            .capacity(3)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .build();
+        assert_eq!(cache.cleanup_mode(), CleanupMode::OnDemand);
+
+        cache.put("a", 1, Some(Duration::ZERO));
+        cache.put("b", 2, Some(Duration::ZERO));
+        cache.get(&"c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.len(), 1);
+
+        let mut cache = LruCache::builder()
+            .capacity(3)
+            .cleanup_mode(CleanupMode::OnAccess)
+            .build();
+        cache.put("a", 1, Some(Duration::ZERO));
+        cache.put("b", 2, Some(Duration::ZERO));
+        cache.get(&"c");
+        assert!(cache.is_empty());
+    }
+}
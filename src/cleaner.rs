@@ -0,0 +1,145 @@
+use std::hash::{BuildHasher, Hash};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::LruCache;
+
+/// Фоновый поток очистки, см. [`LruCache::spawn_cleaner`]
+///
+/// Поток завершается при сбросе или [`Cleaner::stop`] этого дескриптора,
+/// а также после удаления самого кеша.
+pub struct Cleaner {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Cleaner {
+    /// Останавливает поток и дожидается его завершения
+    pub fn stop(self) {
+        // остановка выполняется в `Drop`
+    }
+
+    /// Поток уже завершился (например, потому что кеш удалён)
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+}
+
+impl Drop for Cleaner {
+    fn drop(&mut self) {
+        // закрытие канала будит поток, не дожидаясь конца интервала
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Eq + Hash + Send + 'static,
+    V: Send + 'static,
+    S: BuildHasher + Send + 'static,
+{
+    /// Запускает поток, удаляющий просроченные элементы раз в `interval`,
+    /// чтобы память освобождалась и в простаивающем кеше; заодно выполняются
+    /// отложенные обновления, см. [`LruCache::run_pending_refreshes`]
+    ///
+    /// Поток держит только слабую ссылку и не продлевает жизнь кеша.
+    pub fn spawn_cleaner(cache: &Arc<Mutex<Self>>, interval: Duration) -> Cleaner {
+        let cache = Arc::downgrade(cache);
+        Cleaner::spawn(interval, move || {
+            let Some(cache) = cache.upgrade() else {
+                return false;
+            };
+            let Ok(mut cache) = cache.lock() else {
+                return false;
+            };
+            cache.run_maintenance();
+            true
+        })
+    }
+
+    /// Один проход фоновой очистки
+    pub(crate) fn run_maintenance(&mut self) {
+        self.evict_expired();
+        self.run_pending_refreshes();
+        #[cfg(feature = "metrics")]
+        self.publish_metrics();
+    }
+}
+
+impl Cleaner {
+    /// Поток, вызывающий `tick` раз в `interval`, пока тот возвращает `true`
+    pub(crate) fn spawn(
+        interval: Duration,
+        mut tick: impl FnMut() -> bool + Send + 'static,
+    ) -> Cleaner {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let thread = thread::Builder::new()
+            .name("lru-cache-cleaner".into())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if !tick() {
+                        break;
+                    }
+                }
+            })
+            .expect("не удалось запустить поток очистки");
+
+        Cleaner {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+    use std::time::Instant;
+
+    /// Ждёт выполнения условия, проверяя его до истечения запаса времени
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() {
+            assert!(Instant::now() < deadline, "условие не выполнилось");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_spawn_cleaner() {
+        let clock = MockClock::new();
+        let cache = Arc::new(Mutex::new(
+            LruCache::builder()
+                .capacity(10)
+                .cleanup_mode(CleanupMode::OnDemand)
+                .clock(clock.clone())
+                .build(),
+        ));
+        {
+            let mut cache = cache.lock().unwrap();
+            cache.put("a", 1, Some(Duration::from_secs(1)));
+            cache.put("b", 2, None);
+        }
+
+        let cleaner = LruCache::spawn_cleaner(&cache, Duration::from_millis(1));
+        clock.advance(Duration::from_secs(2));
+        wait_until(|| cache.lock().unwrap().len() == 1);
+        assert!(!cleaner.is_finished());
+
+        drop(cache);
+        wait_until(|| cleaner.is_finished());
+
+        let cache = Arc::new(Mutex::new(LruCache::<i32, i32>::new(
+            10,
+            CleanupMode::OnDemand,
+        )));
+        LruCache::spawn_cleaner(&cache, Duration::from_secs(3600)).stop();
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Источник текущего времени для расчёта TTL, см. [`LruCacheBuilder::clock`](crate::LruCacheBuilder::clock)
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// Системные часы, `Instant::now()`; используются по умолчанию
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Часы на основе счётчика тактов процессора ([quanta](https://crates.io/crates/quanta)),
+/// по умолчанию при включённой опции `quanta`
+///
+/// Калибровка счётчика выполняется один раз при создании первых часов,
+/// показания переводятся в `Instant` относительно момента создания.
+#[cfg(feature = "quanta")]
+#[derive(Debug, Clone)]
+pub struct QuantaClock {
+    clock: quanta::Clock,
+    base: Instant,
+    base_raw: quanta::Instant,
+}
+
+#[cfg(feature = "quanta")]
+impl QuantaClock {
+    pub fn new() -> Self {
+        let clock = quanta::Clock::new();
+        QuantaClock {
+            base_raw: clock.now(),
+            base: Instant::now(),
+            clock,
+        }
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl Default for QuantaClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl Clock for QuantaClock {
+    fn now(&self) -> Instant {
+        self.base + self.clock.now().saturating_duration_since(self.base_raw)
+    }
+}
+
+/// Часы кеша по умолчанию: [`QuantaClock`] при включённой опции `quanta`, иначе [`SystemClock`]
+pub(crate) fn default_clock() -> Arc<dyn Clock + Send + Sync> {
+    #[cfg(feature = "quanta")]
+    return Arc::new(QuantaClock::new());
+    #[cfg(not(feature = "quanta"))]
+    Arc::new(SystemClock)
+}
+
+/// Управляемые часы для тестов и симуляций: время идёт только через [`MockClock::advance`]
+///
+/// Копии разделяют общее состояние, поэтому одну копию можно передать кешу,
+/// а другой продвигать время.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    base: Instant,
+    offset_nanos: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        MockClock {
+            base: Instant::now(),
+            offset_nanos: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Продвигает часы на `duration` для всех копий
+    pub fn advance(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.offset_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.base + Duration::from_nanos(self.offset_nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// Грубые часы для высоконагруженных кешей: время обновляется фоновым потоком
+/// раз в `resolution`, и `now()` сводится к чтению атомарной переменной
+///
+/// Сроки истечения при этом смещаются не больше чем на `resolution`.
+/// Поток завершается после сброса последней копии часов.
+#[derive(Debug, Clone)]
+pub struct CoarseClock {
+    base: Instant,
+    offset_nanos: Arc<AtomicU64>,
+}
+
+impl CoarseClock {
+    pub fn new(resolution: Duration) -> Self {
+        let base = Instant::now();
+        let offset_nanos = Arc::new(AtomicU64::new(0));
+        let shared = Arc::downgrade(&offset_nanos);
+
+        thread::Builder::new()
+            .name("lru-cache-coarse-clock".into())
+            .spawn(move || {
+                loop {
+                    thread::sleep(resolution);
+                    let Some(offset_nanos) = shared.upgrade() else {
+                        break;
+                    };
+                    let elapsed = u64::try_from(base.elapsed().as_nanos()).unwrap_or(u64::MAX);
+                    offset_nanos.store(elapsed, Ordering::Relaxed);
+                }
+            })
+            .expect("не удалось запустить поток часов");
+
+        CoarseClock { base, offset_nanos }
+    }
+}
+
+impl Clock for CoarseClock {
+    fn now(&self) -> Instant {
+        self.base + Duration::from_nanos(self.offset_nanos.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, ExpiryIndex, LruCache, Ttl};
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+
+        cache.put("a", 1, Some(Duration::from_secs(60)));
+        cache.put("b", 2, Some(Duration::from_secs(3600)));
+        cache.put("c", 3, None);
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(
+            cache.ttl(&"a"),
+            Some(Ttl::Remaining(Duration::from_secs(30)))
+        );
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.len(), 2);
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"c"]);
+        cache.evict_expired();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_coarse_clock() {
+        let clock = CoarseClock::new(Duration::from_millis(5));
+        let start = clock.now();
+        assert!(start <= Instant::now());
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(clock.now() > start);
+        assert!(clock.now() <= Instant::now());
+    }
+
+    #[cfg(feature = "quanta")]
+    #[test]
+    fn test_quanta_clock() {
+        let clock = QuantaClock::new();
+        let before = Instant::now();
+        thread::sleep(Duration::from_millis(20));
+        let now = clock.now();
+
+        assert!(now >= before + Duration::from_millis(15));
+        assert!(now <= Instant::now() + Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_mock_clock_timer_wheel() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .cleanup_mode(CleanupMode::OnDemand)
+            .expiry_index(ExpiryIndex::TimerWheel)
+            .clock(clock.clone())
+            .build();
+
+        for i in 0..100u64 {
+            cache.put(i, i, Some(Duration::from_secs(i + 1)));
+        }
+
+        clock.advance(Duration::from_secs(50));
+        cache.evict_expired();
+        assert_eq!(cache.len(), 50);
+
+        clock.advance(Duration::from_secs(3600 * 24 * 1000));
+        cache.evict_expired();
+        assert!(cache.is_empty());
+    }
+}
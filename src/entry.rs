@@ -0,0 +1,222 @@
+use std::hash::{BuildHasher, Hash};
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use crate::{DefaultHashBuilder, LruCache, Node, RemovalCause};
+
+/// Элемент кеша, полученный через [`LruCache::entry`]
+///
+/// Существующий элемент уже отмечен как недавно использованный,
+/// новый элемент становится недавно использованным при вставке.
+pub enum Entry<'a, K: Eq + Hash, V, S: BuildHasher = DefaultHashBuilder> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+/// Существующий элемент
+pub struct OccupiedEntry<'a, K: Eq + Hash, V, S: BuildHasher = DefaultHashBuilder> {
+    pub(crate) cache: &'a mut LruCache<K, V, S>,
+    pub(crate) node_ptr: NonNull<Node<K, V>>,
+}
+
+// элемент ведёт себя как `&mut LruCache`, указатель на узел не покидает его
+unsafe impl<K: Eq + Hash + Send, V: Send, S: BuildHasher + Send> Send
+    for OccupiedEntry<'_, K, V, S>
+{
+}
+unsafe impl<K: Eq + Hash + Sync, V: Sync, S: BuildHasher + Sync> Sync
+    for OccupiedEntry<'_, K, V, S>
+{
+}
+
+/// Отсутствующий элемент
+pub struct VacantEntry<'a, K: Eq + Hash, V, S: BuildHasher = DefaultHashBuilder> {
+    pub(crate) cache: &'a mut LruCache<K, V, S>,
+    pub(crate) key: K,
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> Entry<'a, K, V, S> {
+    /// Вставляет `default` с TTL кеша по умолчанию, если элемента нет
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default, None),
+        }
+    }
+
+    /// Вставляет результат `default()` с TTL кеша по умолчанию, если элемента нет
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default(), None),
+        }
+    }
+
+    /// Изменяет существующий элемент на месте и пересчитывает его вес
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                let now = entry.cache.now();
+                entry.cache.reweigh(entry.node_ptr, now);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node_ptr.as_ptr()).key }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { &(*self.node_ptr.as_ptr()).value }
+    }
+
+    /// Изменяемый доступ к значению; вес изменённого значения не пересчитывается,
+    /// поэтому в кеше с функцией веса используйте [`OccupiedEntry::insert`] или [`LruCache::get_guard`]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.node_ptr.as_ptr()).value }
+    }
+
+    /// Как [`OccupiedEntry::get_mut`], вес не пересчитывается
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut (*self.node_ptr.as_ptr()).value }
+    }
+
+    /// Заменяет значение, сохраняя время жизни, и возвращает прежнее
+    ///
+    /// Если новое значение тяжелее, лишние элементы вытесняются.
+    pub fn insert(&mut self, value: V) -> V {
+        let old = std::mem::replace(self.get_mut(), value);
+        let now = self.cache.now();
+        self.cache.reweigh(self.node_ptr, now);
+        old
+    }
+
+    /// Удаляет элемент из кеша и возвращает его значение
+    pub fn remove(self) -> V {
+        let node = self.cache.unlink(self.node_ptr);
+        self.cache
+            .notify_returned(&node.key, &node.value, RemovalCause::Explicit);
+        node.value
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Вставляет значение с опциональным временем жизни (`None` - TTL кеша по умолчанию)
+    ///
+    /// Отключённый кеш (ёмкость 0) не сохраняет элемент: ссылка указывает
+    /// на временную копию, которая будет сброшена следующей вставкой.
+    pub fn insert(self, value: V, ttl: Option<Duration>) -> &'a mut V {
+        if self.cache.is_disabled() {
+            return self.cache.scratch.insert(value);
+        }
+
+        let now = self.cache.now();
+        let expires_at = self.cache.expires_at_for(&self.key, &value, ttl, now);
+        let node_ptr = self.cache.insert_new(self.key, value, expires_at, now);
+        self.cache.evict_overflow(node_ptr, now);
+        unsafe { &mut (*node_ptr.as_ptr()).value }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+
+    #[test]
+    fn test_entry_or_insert() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+
+        *cache.entry("a").or_insert(0) += 1;
+        *cache.entry("a").or_insert(0) += 1;
+        assert_eq!(cache.get(&"a"), Some(&2));
+
+        let value = cache.entry("b").or_insert_with(|| 10);
+        assert_eq!(*value, 10);
+
+        // обращение через entry продвигает "a", поэтому вытесняется "b"
+        cache.entry("a").or_insert(0);
+        cache.entry("c").or_insert(3);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"c", &"a"]);
+    }
+
+    #[test]
+    fn test_entry_and_modify() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+
+        cache.entry("a").and_modify(|v| *v += 1).or_insert(1);
+        assert_eq!(cache.peek(&"a"), Some(&1));
+        cache.entry("a").and_modify(|v| *v += 1).or_insert(1);
+        assert_eq!(cache.peek(&"a"), Some(&2));
+    }
+
+    #[test]
+    fn test_entry_reweigh() {
+        let mut cache = LruCache::builder()
+            .max_weight(10)
+            .weigher(|_: &&str, v: &Vec<u8>| v.len() as u32)
+            .build();
+        cache.put("a", vec![0; 2], None);
+        cache.put("b", vec![0; 2], None);
+
+        cache.entry("b").and_modify(|v| v.extend([0; 4]));
+        assert_eq!(cache.total_weight(), 8);
+        if let Entry::Occupied(mut entry) = cache.entry("b") {
+            entry.insert(vec![0; 100]);
+        }
+        // "a" вытеснен, тяжёлый "b" остаётся один
+        assert_eq!(cache.total_weight(), 100);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_variants() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+        cache.put("a", 1, Some(Duration::from_secs(1)));
+
+        match cache.entry("a") {
+            Entry::Occupied(mut entry) => assert_eq!(entry.insert(5), 1),
+            Entry::Vacant(_) => panic!("entry must be occupied"),
+        }
+
+        clock.advance(Duration::from_secs(2));
+        match cache.entry("a") {
+            Entry::Occupied(_) => panic!("expired entry must be vacant"),
+            Entry::Vacant(entry) => {
+                entry.insert(7, None);
+            }
+        }
+        assert_eq!(cache.peek(&"a"), Some(&7));
+
+        match cache.entry("a") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 7),
+            Entry::Vacant(_) => panic!("entry must be occupied"),
+        }
+        assert!(cache.is_empty());
+    }
+}
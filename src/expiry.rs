@@ -0,0 +1,314 @@
+use std::collections::BTreeSet;
+use std::ptr::NonNull;
+use std::time::Instant;
+
+use crate::Node;
+
+/// Структура индекса истечения, выбирается в [`LruCacheBuilder::expiry_index`](crate::LruCacheBuilder::expiry_index)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpiryIndex {
+    /// Упорядоченное множество: точный порядок, O(log n) на вставку и удаление
+    #[default]
+    Ordered,
+    /// Иерархическое колесо таймеров с шагом 1 мс: амортизированное O(1)
+    /// на планирование и очистку, выгоднее при миллионах элементов с TTL
+    TimerWheel,
+}
+
+/// Индекс узлов с ограниченным временем жизни
+pub(crate) enum ExpiryQueue<K, V> {
+    // указатель в паре делает ключ уникальным при совпадающих моментах
+    Ordered(BTreeSet<(Instant, NonNull<Node<K, V>>)>),
+    Wheel(TimerWheel<K, V>),
+}
+
+impl<K, V> ExpiryQueue<K, V> {
+    /// Новый индекс; `now` - начало отсчёта тиков колеса таймеров
+    pub(crate) fn new(index: ExpiryIndex, now: Instant) -> Self {
+        match index {
+            ExpiryIndex::Ordered => ExpiryQueue::Ordered(BTreeSet::new()),
+            ExpiryIndex::TimerWheel => ExpiryQueue::Wheel(TimerWheel::new(now)),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, node_ptr: NonNull<Node<K, V>>, expires_at: Instant) {
+        match self {
+            ExpiryQueue::Ordered(set) => {
+                set.insert((expires_at, node_ptr));
+            }
+            ExpiryQueue::Wheel(wheel) => wheel.insert(node_ptr, expires_at),
+        }
+    }
+
+    /// Удаление узла; для уже извлечённого узла ничего не делает
+    pub(crate) fn remove(&mut self, node_ptr: NonNull<Node<K, V>>, expires_at: Instant) {
+        match self {
+            ExpiryQueue::Ordered(set) => {
+                set.remove(&(expires_at, node_ptr));
+            }
+            ExpiryQueue::Wheel(wheel) => wheel.remove(node_ptr),
+        }
+    }
+
+    /// Извлекает очередной узел, истёкший к моменту `now`
+    pub(crate) fn pop_expired(&mut self, now: Instant) -> Option<NonNull<Node<K, V>>> {
+        match self {
+            ExpiryQueue::Ordered(set) => {
+                let &(expires_at, node_ptr) = set.first()?;
+                if expires_at > now {
+                    return None;
+                }
+                set.pop_first();
+                Some(node_ptr)
+            }
+            ExpiryQueue::Wheel(wheel) => wheel.pop_expired(now),
+        }
+    }
+
+    pub(crate) fn index(&self) -> ExpiryIndex {
+        match self {
+            ExpiryQueue::Ordered(_) => ExpiryIndex::Ordered,
+            ExpiryQueue::Wheel(_) => ExpiryIndex::TimerWheel,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            ExpiryQueue::Ordered(set) => set.clear(),
+            ExpiryQueue::Wheel(wheel) => wheel.clear(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            ExpiryQueue::Ordered(set) => set.len(),
+            ExpiryQueue::Wheel(wheel) => wheel.len,
+        }
+    }
+}
+
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+// самый дальний срок, который помещается в колесо (около 795 дней);
+// более поздние сроки укорачиваются и переносятся при наступлении
+const MAX_TICKS: u64 = (1 << (SLOT_BITS * LEVELS)) - 1;
+
+/// Положение узла в колесе таймеров
+#[derive(Clone, Copy)]
+pub(crate) struct TimerHandle {
+    slot: usize,
+    index: usize,
+}
+
+impl TimerHandle {
+    pub(crate) const DETACHED: TimerHandle = TimerHandle {
+        slot: usize::MAX,
+        index: 0,
+    };
+}
+
+/// Иерархическое колесо таймеров: уровень `l` делит время на слоты по `64^l` тиков
+///
+/// Узел попадает на уровень старшего различающегося разряда между своим сроком и
+/// текущим тиком, поэтому слоты нижних уровней всегда истекают раньше верхних.
+/// Наступивший слот верхнего уровня раскладывается по нижним, колесо продвигается
+/// лениво при вызове [`TimerWheel::pop_expired`].
+pub(crate) struct TimerWheel<K, V> {
+    start: Instant,
+    elapsed: u64,
+    slots: Vec<Vec<NonNull<Node<K, V>>>>,
+    occupied: [u64; LEVELS],
+    len: usize,
+}
+
+impl<K, V> TimerWheel<K, V> {
+    fn new(start: Instant) -> Self {
+        TimerWheel {
+            start,
+            elapsed: 0,
+            slots: (0..LEVELS * SLOTS).map(|_| Vec::new()).collect(),
+            occupied: [0; LEVELS],
+            len: 0,
+        }
+    }
+
+    /// Номер тика, к которому момент `at` уже наступил (с округлением вверх)
+    fn deadline(&self, at: Instant) -> u64 {
+        let nanos = at.saturating_duration_since(self.start).as_nanos();
+        nanos.div_ceil(1_000_000).min(u64::MAX as u128) as u64
+    }
+
+    fn tick(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_millis() as u64
+    }
+
+    fn insert(&mut self, node_ptr: NonNull<Node<K, V>>, expires_at: Instant) {
+        let deadline = self.deadline(expires_at);
+        self.place(node_ptr, deadline);
+        self.len += 1;
+    }
+
+    fn place(&mut self, node_ptr: NonNull<Node<K, V>>, deadline: u64) {
+        let deadline = deadline.clamp(self.elapsed, self.elapsed + MAX_TICKS);
+        let masked = (self.elapsed ^ deadline) | (SLOTS as u64 - 1);
+        let level = ((63 - masked.leading_zeros() as usize) / SLOT_BITS).min(LEVELS - 1);
+        let slot = (deadline >> (level * SLOT_BITS)) as usize & (SLOTS - 1);
+
+        let bucket = &mut self.slots[level * SLOTS + slot];
+        unsafe {
+            (*node_ptr.as_ptr()).timer = TimerHandle {
+                slot: level * SLOTS + slot,
+                index: bucket.len(),
+            };
+        }
+        bucket.push(node_ptr);
+        self.occupied[level] |= 1 << slot;
+    }
+
+    fn remove(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        let handle = unsafe { (*node_ptr.as_ptr()).timer };
+        if handle.slot == TimerHandle::DETACHED.slot {
+            return;
+        }
+
+        let bucket = &mut self.slots[handle.slot];
+        bucket.swap_remove(handle.index);
+        if let Some(&moved) = bucket.get(handle.index) {
+            unsafe { (*moved.as_ptr()).timer.index = handle.index };
+        }
+        if bucket.is_empty() {
+            self.occupied[handle.slot / SLOTS] &= !(1 << (handle.slot % SLOTS));
+        }
+
+        unsafe { (*node_ptr.as_ptr()).timer = TimerHandle::DETACHED };
+        self.len -= 1;
+    }
+
+    /// Ближайший занятый слот: `(уровень, слот, тик начала слота)`
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        (0..LEVELS).find_map(|level| {
+            let occupied = self.occupied[level];
+            if occupied == 0 {
+                return None;
+            }
+
+            let slot_range = 1u64 << (level * SLOT_BITS);
+            let level_range = slot_range << SLOT_BITS;
+            let now_slot = (self.elapsed / slot_range) as usize % SLOTS;
+            let slot = (occupied.rotate_right(now_slot as u32).trailing_zeros() as usize
+                + now_slot)
+                % SLOTS;
+
+            let mut deadline = (self.elapsed & !(level_range - 1)) + slot as u64 * slot_range;
+            // слот "позади" текущего тика относится к следующему обороту уровня
+            if deadline < self.elapsed || (level > 0 && slot == now_slot) {
+                deadline += level_range;
+            }
+            Some((level, slot, deadline))
+        })
+    }
+
+    fn pop_expired(&mut self, now: Instant) -> Option<NonNull<Node<K, V>>> {
+        let now_tick = self.tick(now).max(self.elapsed);
+
+        loop {
+            let Some((level, slot, deadline)) = self.next_slot().filter(|&(_, _, d)| d <= now_tick)
+            else {
+                self.elapsed = now_tick;
+                return None;
+            };
+            self.elapsed = deadline;
+            self.occupied[level] &= !(1 << slot);
+
+            let bucket = std::mem::take(&mut self.slots[level * SLOTS + slot]);
+            if level == 0 {
+                // все узлы слота нулевого уровня истекают в его тик, очередной
+                // извлекается, остальные возвращаются в слот до следующего вызова
+                let mut bucket = bucket;
+                let node_ptr = bucket.pop()?;
+                if !bucket.is_empty() {
+                    self.occupied[0] |= 1 << slot;
+                }
+                self.slots[slot] = bucket;
+
+                unsafe { (*node_ptr.as_ptr()).timer = TimerHandle::DETACHED };
+                self.len -= 1;
+                return Some(node_ptr);
+            }
+
+            // наступивший слот верхнего уровня раскладывается по нижним
+            for node_ptr in bucket {
+                let deadline = unsafe { (*node_ptr.as_ptr()).expires_at }
+                    .map_or(u64::MAX, |expires_at| self.deadline(expires_at));
+                self.place(node_ptr, deadline);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots.iter_mut().for_each(Vec::clear);
+        self.occupied = [0; LEVELS];
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, LruCache};
+    use std::time::Duration;
+
+    #[test]
+    fn test_timer_wheel_expiry() {
+        let mut cache = LruCache::builder()
+            .capacity(100)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .expiry_index(ExpiryIndex::TimerWheel)
+            .build();
+
+        let now = Instant::now();
+        for i in 0..50u64 {
+            cache.put(i, i, Some(Duration::from_millis(i * 20)));
+        }
+        cache.put(100, 100, Some(Duration::from_secs(3600 * 24 * 1000)));
+        cache.put(101, 101, None);
+        cache.remove(&30);
+        cache.expire_after(&40, Duration::from_secs(60));
+        assert_eq!(cache.expiry.len(), 50);
+
+        // ручное продвижение колеса на заданные моменты без ожидания
+        let reap = |cache: &mut LruCache<u64, u64>, at: Duration| {
+            let mut reaped = Vec::new();
+            while let Some(node_ptr) = cache.expiry.pop_expired(now + at) {
+                reaped.push(unsafe { (*node_ptr.as_ptr()).key });
+                cache.unlink(node_ptr);
+            }
+            reaped.sort();
+            reaped
+        };
+
+        assert_eq!(reap(&mut cache, Duration::from_millis(45)), vec![0, 1, 2]);
+        assert_eq!(
+            reap(&mut cache, Duration::from_millis(45)),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            reap(&mut cache, Duration::from_secs(2)),
+            (3..50).filter(|&i| i != 30 && i != 40).collect::<Vec<_>>()
+        );
+        assert_eq!(reap(&mut cache, Duration::from_secs(3600)), vec![40]);
+        assert_eq!(
+            reap(&mut cache, Duration::from_secs(3600 * 24 * 999)),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            reap(&mut cache, Duration::from_secs(3600 * 24 * 1001)),
+            vec![100]
+        );
+
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&101]);
+        assert_eq!(cache.expiry.len(), 0);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+#[cfg(feature = "tokio")]
+use std::future::{Future, poll_fn};
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::Waker;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+
+/// Незавершённые загрузки по ключам для `get_with` потокобезопасных кешей
+///
+/// Загрузка представлена заглушкой: первый промах становится загрузчиком, остальные
+/// встают в очередь ожидания. Если загрузчик вернул ошибку, запаниковал или был
+/// отменён, заглушка удаляется, а ожидающие повторяют попытку, и один из них
+/// становится новым загрузчиком.
+pub(crate) struct InFlight<K, V> {
+    calls: Mutex<HashMap<K, Arc<Call<V>>>>,
+}
+
+enum State<V> {
+    Loading,
+    Done(V),
+    Abandoned,
+}
+
+struct Slot<V> {
+    state: State<V>,
+    // ожидающие асинхронные задачи; потоки ждут на `Call::ready`
+    wakers: Vec<Waker>,
+}
+
+struct Call<V> {
+    slot: Mutex<Slot<V>>,
+    ready: Condvar,
+}
+
+enum Join<'a, K: Eq + Hash, V> {
+    Load(Placeholder<'a, K, V>),
+    Wait(Arc<Call<V>>),
+}
+
+/// Право на загрузку ключа; сброс без [`Placeholder::fill`] освобождает ожидающих для повтора
+struct Placeholder<'a, K: Eq + Hash, V> {
+    flight: &'a InFlight<K, V>,
+    key: K,
+    call: Arc<Call<V>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // под этими блокировками не вызывается пользовательский код
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V> Default for InFlight<K, V> {
+    fn default() -> Self {
+        InFlight {
+            calls: Mutex::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> InFlight<K, V> {
+    /// Выполняет `load` в единственном потоке; остальные потоки с тем же ключом
+    /// ждут и получают его результат, а после неудачи загружают сами
+    pub(crate) fn try_run<E>(&self, key: &K, load: impl FnOnce() -> Result<V, E>) -> Result<V, E> {
+        let placeholder = loop {
+            match self.join(key) {
+                Join::Load(placeholder) => break placeholder,
+                Join::Wait(call) => {
+                    if let Some(value) = call.wait() {
+                        return Ok(value);
+                    }
+                }
+            }
+        };
+
+        let value = load()?;
+        placeholder.fill(value.clone());
+        Ok(value)
+    }
+
+    /// Асинхронный вариант [`InFlight::try_run`]: ожидание не блокирует поток
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn try_run_async<E, Fut>(
+        &self,
+        key: &K,
+        load: impl FnOnce() -> Fut,
+    ) -> Result<V, E>
+    where
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let placeholder = loop {
+            match self.join(key) {
+                Join::Load(placeholder) => break placeholder,
+                Join::Wait(call) => {
+                    if let Some(value) = poll_fn(|cx| call.poll_wait(cx)).await {
+                        return Ok(value);
+                    }
+                }
+            }
+        };
+
+        let value = load().await?;
+        placeholder.fill(value.clone());
+        Ok(value)
+    }
+
+    fn join(&self, key: &K) -> Join<'_, K, V> {
+        let mut calls = lock(&self.calls);
+        if let Some(call) = calls.get(key) {
+            return Join::Wait(call.clone());
+        }
+
+        let call = Arc::new(Call {
+            slot: Mutex::new(Slot {
+                state: State::Loading,
+                wakers: Vec::new(),
+            }),
+            ready: Condvar::new(),
+        });
+        calls.insert(key.clone(), call.clone());
+        Join::Load(Placeholder {
+            flight: self,
+            key: key.clone(),
+            call,
+        })
+    }
+}
+
+impl<V: Clone> Call<V> {
+    /// Результат загрузки; `None`, если заглушку бросили
+    fn wait(&self) -> Option<V> {
+        let mut slot = lock(&self.slot);
+        while let State::Loading = slot.state {
+            slot = self
+                .ready
+                .wait(slot)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        slot.state.value()
+    }
+
+    #[cfg(feature = "tokio")]
+    fn poll_wait(&self, cx: &mut Context<'_>) -> Poll<Option<V>> {
+        let mut slot = lock(&self.slot);
+        if let State::Loading = slot.state {
+            if !slot.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                slot.wakers.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+        Poll::Ready(slot.state.value())
+    }
+}
+
+impl<V: Clone> State<V> {
+    fn value(&self) -> Option<V> {
+        match self {
+            State::Done(value) => Some(value.clone()),
+            State::Loading | State::Abandoned => None,
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> Placeholder<'_, K, V> {
+    fn fill(self, value: V) {
+        lock(&self.call.slot).state = State::Done(value);
+        // ожидающие будятся в `drop`
+    }
+}
+
+impl<K: Eq + Hash, V> Drop for Placeholder<'_, K, V> {
+    fn drop(&mut self) {
+        {
+            let mut calls = lock(&self.flight.calls);
+            if calls
+                .get(&self.key)
+                .is_some_and(|current| Arc::ptr_eq(current, &self.call))
+            {
+                calls.remove(&self.key);
+            }
+        }
+
+        let wakers = {
+            let mut slot = lock(&self.call.slot);
+            if let State::Loading = slot.state {
+                slot.state = State::Abandoned;
+            }
+            std::mem::take(&mut slot.wakers)
+        };
+        self.call.ready.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_abandoned_load() {
+        let flight = Arc::new(InFlight::<&str, u32>::default());
+        let loads = Arc::new(AtomicU32::new(0));
+
+        let load = |result: Result<u32, ()>| {
+            let (flight, loads) = (flight.clone(), loads.clone());
+            thread::spawn(move || {
+                flight.try_run(&"a", || {
+                    loads.fetch_add(1, Ordering::Relaxed);
+                    thread::sleep(Duration::from_millis(50));
+                    result
+                })
+            })
+        };
+
+        // загрузчик вернул ошибку: ожидающий загружает сам
+        let failed = load(Err(()));
+        thread::sleep(Duration::from_millis(10));
+        let waiter = load(Ok(2));
+        assert_eq!(failed.join().unwrap(), Err(()));
+        assert_eq!(waiter.join().unwrap(), Ok(2));
+        assert_eq!(loads.load(Ordering::Relaxed), 2);
+
+        // паника загрузчика тоже освобождает ожидающих
+        let panicked = {
+            let flight = flight.clone();
+            thread::spawn(move || {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    flight.try_run(&"a", || -> Result<u32, ()> {
+                        thread::sleep(Duration::from_millis(50));
+                        panic!("загрузка не удалась")
+                    })
+                }))
+                .is_err()
+            })
+        };
+        thread::sleep(Duration::from_millis(10));
+        let waiter = load(Ok(3));
+        assert!(panicked.join().unwrap());
+        assert_eq!(waiter.join().unwrap(), Ok(3));
+        assert!(lock(&flight.calls).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+use crate::{DefaultHashBuilder, LruCache, Node};
+
+/// Доступ к значению, удерживающий кеш, см. [`LruCache::get_guard`]
+///
+/// Пока страж жив, кеш заимствован, поэтому элемент не может быть вытеснен или удалён.
+/// При сбросе обновляется время последнего доступа и пересчитывается вес значения:
+/// если изменённое значение стало тяжелее, лишние элементы вытесняются.
+pub struct ValueGuard<'a, K: Eq + Hash, V, S: BuildHasher = DefaultHashBuilder> {
+    cache: &'a mut LruCache<K, V, S>,
+    node_ptr: NonNull<Node<K, V>>,
+}
+
+// страж ведёт себя как `&mut LruCache`, указатель на узел не покидает его
+unsafe impl<K: Eq + Hash + Send, V: Send, S: BuildHasher + Send> Send for ValueGuard<'_, K, V, S> {}
+unsafe impl<K: Eq + Hash + Sync, V: Sync, S: BuildHasher + Sync> Sync for ValueGuard<'_, K, V, S> {}
+
+impl<K: Eq + Hash, V, S: BuildHasher> ValueGuard<'_, K, V, S> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node_ptr.as_ptr()).key }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Deref for ValueGuard<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { &(*self.node_ptr.as_ptr()).value }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> DerefMut for ValueGuard<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.node_ptr.as_ptr()).value }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Drop for ValueGuard<'_, K, V, S> {
+    fn drop(&mut self) {
+        let now = self.cache.now();
+        unsafe { (*self.node_ptr.as_ptr()).accessed_at = now };
+        self.cache.reweigh(self.node_ptr, now);
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Чтение как в [`LruCache::get_mut`], но через страж [`ValueGuard`]
+    pub fn get_guard<Q>(&mut self, key: &Q) -> Option<ValueGuard<'_, K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = self.read_node(key)?;
+        Some(ValueGuard {
+            cache: self,
+            node_ptr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+    use std::time::Duration;
+
+    #[test]
+    fn test_value_guard() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .max_weight(10)
+            .weigher(|_: &&str, v: &Vec<u8>| v.len() as u32)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+        cache.put("a", vec![0; 4], None);
+        cache.put("b", vec![0; 4], None);
+
+        {
+            let mut guard = cache.get_guard(&"a").unwrap();
+            assert_eq!(guard.key(), &"a");
+            guard.extend([1, 2, 3]);
+            clock.advance(Duration::from_secs(5));
+        }
+
+        // значение потяжелело: вытеснен давно использованный "b"
+        assert_eq!(cache.total_weight(), 7);
+        assert!(!cache.contains_key(&"b"));
+        let meta = cache.metadata(&"a").unwrap();
+        assert_eq!(meta.idle, Duration::ZERO);
+        assert_eq!(meta.age, Duration::from_secs(5));
+        assert!(cache.get_guard(&"x").is_none());
+    }
+}
@@ -0,0 +1,129 @@
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+/// Точки наблюдения за обращениями к кешу, например для внешнего контроллера допуска
+/// (TinyLFU), см. [`LruCacheBuilder::hooks`](crate::LruCacheBuilder::hooks)
+///
+/// Все методы по умолчанию ничего не делают.
+pub trait AccessHooks<K> {
+    /// Добавлен новый ключ (перезапись существующего не считается)
+    fn on_insert(&self, key: &K) {
+        let _ = key;
+    }
+
+    /// Чтение через `get`, `get_mut`, `get_stale` или `entry` нашло элемент
+    fn on_hit(&self, key: &K) {
+        let _ = key;
+    }
+
+    /// Чтение не нашло непросроченного элемента
+    fn on_miss(&self, key: MissedKey<'_, K>) {
+        let _ = key;
+    }
+}
+
+/// Ключ промаха в заимствованной форме, по которой шёл поиск (например, `&str` для `String`)
+///
+/// Его можно сравнить с ключом кеша, а хеш любым хешером совпадает с хешем
+/// соответствующего `K`, как того требует контракт `Borrow`, поэтому внешний
+/// контроллер может вести счётчики по своему хешеру.
+#[derive(Clone, Copy)]
+pub struct MissedKey<'a, K> {
+    key: &'a dyn Lookup<K>,
+}
+
+impl<'a, K> MissedKey<'a, K> {
+    pub(crate) fn new<Q>(key: &'a Borrowed<'a, Q>) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        MissedKey { key }
+    }
+
+    /// Совпадает ли промах с ключом `key`
+    pub fn matches(&self, key: &K) -> bool {
+        self.key.matches(key)
+    }
+}
+
+impl<K> Hash for MissedKey<'_, K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash_into(state);
+    }
+}
+
+/// Заимствованная форма ключа фиксированного размера, чтобы её можно было передать как `dyn`
+pub(crate) struct Borrowed<'a, Q: ?Sized>(pub(crate) &'a Q);
+
+trait Lookup<K> {
+    fn matches(&self, key: &K) -> bool;
+
+    fn hash_into(&self, state: &mut dyn Hasher);
+}
+
+impl<K: Borrow<Q>, Q: Hash + Eq + ?Sized> Lookup<K> for Borrowed<'_, Q> {
+    fn matches(&self, key: &K) -> bool {
+        key.borrow() == self.0
+    }
+
+    fn hash_into(&self, mut state: &mut dyn Hasher) {
+        self.0.hash(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LruCache;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{BuildHasher, BuildHasherDefault};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Log(Mutex<Vec<String>>);
+
+    impl AccessHooks<&'static str> for Arc<Log> {
+        fn on_insert(&self, key: &&'static str) {
+            self.0.lock().unwrap().push(format!("insert {key}"));
+        }
+
+        fn on_hit(&self, key: &&'static str) {
+            self.0.lock().unwrap().push(format!("hit {key}"));
+        }
+
+        fn on_miss(&self, key: MissedKey<'_, &'static str>) {
+            let name = ["a", "b"].into_iter().find(|name| key.matches(name));
+            // хеш промаха совпадает с хешем ключа при любом хешере
+            let hasher = BuildHasherDefault::<DefaultHasher>::default();
+            assert_eq!(hasher.hash_one(key), hasher.hash_one("b"));
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("miss {}", name.unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_access_hooks() {
+        let log = Arc::new(Log::default());
+        let mut cache = LruCache::builder().capacity(10).hooks(log.clone()).build();
+
+        cache.put("a", 1, None);
+        cache.put("a", 2, None);
+        cache.get(&"a");
+        cache.get(&"b");
+        cache.entry("b").or_insert(3);
+
+        assert_eq!(
+            *log.0.lock().unwrap(),
+            vec![
+                "insert a".to_string(),
+                "hit a".to_string(),
+                "miss b".to_string(),
+                "miss b".to_string(),
+                "insert b".to_string(),
+            ]
+        );
+    }
+}
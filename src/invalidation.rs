@@ -0,0 +1,220 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{DefaultHashBuilder, LruCache};
+
+/// Канал рассылки инвалидаций между экземплярами кеша, см. [`InvalidatingCache`]
+///
+/// Каждый экземпляр подписывается и получает идентификатор; сообщения доставляются
+/// всем подписчикам, кроме отправителя. Получение устроено опросом, поэтому адаптер
+/// для Redis или NATS может копить сообщения своей подписки в буфере и отдавать их в `poll`.
+pub trait InvalidationBus<K> {
+    type Error;
+
+    /// Новая подписка, возвращает её идентификатор
+    fn subscribe(&self) -> Result<u64, Self::Error>;
+
+    /// Рассылает `key` всем подписчикам, кроме `from`
+    fn publish(&self, from: u64, key: &K) -> Result<(), Self::Error>;
+
+    /// Ключи, полученные подпиской `subscriber` с прошлого вызова
+    fn poll(&self, subscriber: u64) -> Result<Vec<K>, Self::Error>;
+
+    /// Закрывает подписку: сообщения для неё больше не копятся
+    fn unsubscribe(&self, subscriber: u64) -> Result<(), Self::Error>;
+}
+
+/// Канал инвалидаций внутри процесса
+///
+/// Копии разделяют общее состояние, поэтому копию можно передать каждому экземпляру кеша.
+#[derive(Clone)]
+pub struct LocalBus<K> {
+    queues: Arc<Mutex<HashMap<u64, Vec<K>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl<K> LocalBus<K> {
+    pub fn new() -> Self {
+        LocalBus {
+            queues: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl<K> Default for LocalBus<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone> InvalidationBus<K> for LocalBus<K> {
+    type Error = Infallible;
+
+    fn subscribe(&self) -> Result<u64, Infallible> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.queues.lock().unwrap().insert(id, Vec::new());
+        Ok(id)
+    }
+
+    fn publish(&self, from: u64, key: &K) -> Result<(), Infallible> {
+        let mut queues = self.queues.lock().unwrap();
+        for (_, queue) in queues.iter_mut().filter(|(id, _)| **id != from) {
+            queue.push(key.clone());
+        }
+        Ok(())
+    }
+
+    fn poll(&self, subscriber: u64) -> Result<Vec<K>, Infallible> {
+        let mut queues = self.queues.lock().unwrap();
+        Ok(queues
+            .get_mut(&subscriber)
+            .map(std::mem::take)
+            .unwrap_or_default())
+    }
+
+    fn unsubscribe(&self, subscriber: u64) -> Result<(), Infallible> {
+        self.queues.lock().unwrap().remove(&subscriber);
+        Ok(())
+    }
+}
+
+/// Локальный кеш экземпляра, согласованный с другими экземплярами через [`InvalidationBus`]
+///
+/// `put` и `remove` публикуют ключ, и другие экземпляры удаляют свою копию при следующем
+/// обращении (`get`) или при явном вызове `apply_invalidations`, например из фоновой задачи.
+/// При сбросе подписка закрывается.
+pub struct InvalidatingCache<K, V, B: InvalidationBus<K>, S = DefaultHashBuilder> {
+    cache: LruCache<K, V, S>,
+    bus: B,
+    id: u64,
+}
+
+impl<K, V, B, S> InvalidatingCache<K, V, B, S>
+where
+    K: Eq + Hash,
+    B: InvalidationBus<K>,
+    S: BuildHasher,
+{
+    pub fn new(cache: LruCache<K, V, S>, bus: B) -> Result<Self, B::Error> {
+        let id = bus.subscribe()?;
+        Ok(InvalidatingCache { cache, bus, id })
+    }
+
+    /// Чтение после применения полученных инвалидаций
+    pub fn get<Q>(&mut self, key: &Q) -> Result<Option<&V>, B::Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.apply_invalidations()?;
+        Ok(self.cache.get(key))
+    }
+
+    /// Добавление элемента, см. [`LruCache::put`], с рассылкой ключа
+    pub fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Result<Option<V>, B::Error> {
+        self.apply_invalidations()?;
+        self.bus.publish(self.id, &key)?;
+        Ok(self.cache.put(key, value, ttl))
+    }
+
+    /// Удаление элемента с рассылкой ключа после применения полученных инвалидаций
+    pub fn remove<Q>(&mut self, key: &Q) -> Result<Option<V>, B::Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.apply_invalidations()?;
+        self.bus.publish(self.id, &key.to_owned())?;
+        Ok(self.cache.remove(key))
+    }
+
+    /// Удаляет ключи, полученные от других экземпляров; возвращает число удалённых элементов
+    pub fn apply_invalidations(&mut self) -> Result<usize, B::Error> {
+        let keys = self.bus.poll(self.id)?;
+        Ok(keys
+            .iter()
+            .filter(|key| self.cache.remove(*key).is_some())
+            .count())
+    }
+
+    pub fn cache(&self) -> &LruCache<K, V, S> {
+        &self.cache
+    }
+
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
+
+    /// Идентификатор подписки этого экземпляра
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<K, V, B: InvalidationBus<K>, S> Drop for InvalidatingCache<K, V, B, S> {
+    fn drop(&mut self) {
+        // ошибку закрытия подписки вернуть некому
+        let _ = self.bus.unsubscribe(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_invalidation_bus() {
+        let bus = LocalBus::new();
+        let mut first =
+            InvalidatingCache::new(LruCache::new(8, CleanupMode::OnAccess), bus.clone()).unwrap();
+        let mut second =
+            InvalidatingCache::new(LruCache::new(8, CleanupMode::OnAccess), bus.clone()).unwrap();
+
+        first.put("a", 1, None).unwrap();
+        second.put("b", 2, None).unwrap();
+        second.put("a", 10, None).unwrap();
+
+        // запись "a" во втором экземпляре удаляет копию в первом, собственные
+        // сообщения экземпляру не доставляются
+        assert_eq!(first.get(&"a").unwrap(), None);
+        assert_eq!(second.get(&"a").unwrap(), Some(&10));
+
+        first.put("b", 3, None).unwrap();
+        second.remove(&"b").unwrap();
+        assert_eq!(first.apply_invalidations().unwrap(), 1);
+        assert!(first.cache().is_empty());
+        assert_eq!(second.apply_invalidations().unwrap(), 0);
+
+        // сброшенный экземпляр больше не получает сообщений
+        drop(second);
+        first.put("c", 4, None).unwrap();
+        assert_eq!(bus.queues.lock().unwrap().len(), 1);
+        assert!(bus.queues.lock().unwrap()[&first.id()].is_empty());
+    }
+
+    #[test]
+    fn test_invalidation_borrowed_keys() {
+        let bus = LocalBus::new();
+        let mut first =
+            InvalidatingCache::new(LruCache::new(8, CleanupMode::OnAccess), bus.clone()).unwrap();
+        let mut second =
+            InvalidatingCache::new(LruCache::new(8, CleanupMode::OnAccess), bus.clone()).unwrap();
+
+        first.put("a".to_string(), 1, None).unwrap();
+        first.put("b".to_string(), 2, None).unwrap();
+        second.put("a".to_string(), 10, None).unwrap();
+        assert_eq!(second.get("a").unwrap(), Some(&10));
+
+        // удаление сначала применяет полученную инвалидацию "a"
+        assert_eq!(first.remove("b").unwrap(), Some(2));
+        assert!(first.cache().is_empty());
+        assert_eq!(second.apply_invalidations().unwrap(), 0);
+    }
+}
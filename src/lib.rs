@@ -1,320 +1,3419 @@
+use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
 use std::ptr::NonNull;
-use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "tokio")]
+mod async_cache;
+mod buffer;
+mod builder;
+mod cleaner;
+mod clock;
+mod entry;
+mod expiry;
+mod flight;
+mod guard;
+mod hooks;
+mod invalidation;
+mod listener;
+mod loading;
+#[cfg(feature = "tokio")]
+mod maintenance;
+#[cfg(feature = "deepsize")]
+mod memory;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod negative;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod policy;
+mod refresh;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sharded;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "snapshot")]
+mod spill;
+mod stats;
+mod store;
+#[cfg(feature = "futures")]
+mod stream;
+mod sync;
+mod tags;
+mod tiered;
+#[cfg(feature = "snapshot")]
+mod wal;
+mod weigher;
+
+#[cfg(feature = "tokio")]
+pub use async_cache::AsyncLruCache;
+pub use builder::LruCacheBuilder;
+pub use cleaner::Cleaner;
+#[cfg(feature = "quanta")]
+pub use clock::QuantaClock;
+pub use clock::{Clock, CoarseClock, MockClock, SystemClock};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use expiry::ExpiryIndex;
+pub use guard::ValueGuard;
+pub use hooks::{AccessHooks, MissedKey};
+pub use invalidation::{InvalidatingCache, InvalidationBus, LocalBus};
+pub use listener::{RemovalCause, RemovalListener};
+#[cfg(feature = "tokio")]
+pub use loading::AsyncLoadingCache;
+pub use loading::LoadingCache;
+pub use negative::Cached;
+pub use policy::{Expirable, Expiry, ValueExpiry};
+pub use refresh::{Loader, RefreshMode};
+pub use sharded::{ShardStats, ShardedLruCache};
+#[cfg(feature = "snapshot")]
+pub use snapshot::SnapshotError;
+#[cfg(feature = "snapshot")]
+pub use spill::{DiskStore, SpilloverCache};
+pub use stats::CacheStats;
+#[cfg(feature = "sled")]
+pub use store::SledStore;
+pub use store::{BackedCache, BackingStore, WriteMode};
+#[cfg(feature = "futures")]
+pub use stream::{Backpressure, RemovalSender, RemovalStream, removal_stream};
+pub use sync::{ArcLruCache, SyncLruCache};
+pub use tiered::{CacheTier, TieredCache};
+#[cfg(feature = "snapshot")]
+pub use wal::WalLruCache;
+pub use weigher::Weigher;
+
+use expiry::{ExpiryQueue, TimerHandle};
+use hooks::Borrowed;
+use negative::NegativeEntries;
+use refresh::RefreshAhead;
+use stats::Stats;
+use tags::TagIndex;
 
 struct Node<K, V> {
     key: K,
     value: V,
+    weight: u32,
+    // узел стоит в очереди отложенного обновления, см. [`RefreshMode::Deferred`]
+    refresh_queued: bool,
     expires_at: Option<Instant>,
+    timer: TimerHandle,
+    // сведения для [`LruCache::metadata`]
+    inserted_at: Instant,
+    accessed_at: Instant,
+    hits: u32,
     next: Option<NonNull<Node<K, V>>>,
     prev: Option<NonNull<Node<K, V>>>,
 }
 
+/// Перехваченные удалённые элементы, см. [`LruCache::capture_evictions`]
+struct Demoted<K, V> {
+    // вытесненные по ёмкости вместе с моментом истечения
+    evicted: Vec<(K, V, Option<Instant>)>,
+    // просроченные; `None`, если они не перехватываются
+    expired: Option<Vec<(K, V)>>,
+}
+
+/// Ключ карты, указывающий на ключ внутри узла, поэтому ключ хранится в единственном экземпляре
+///
+/// Узел живёт в `Box` и не перемещается, пока присутствует в карте,
+/// поэтому указатель остаётся валидным всё время жизни записи карты.
+struct KeyRef<K> {
+    key: *const K,
+}
+
+impl<K: Hash> Hash for KeyRef<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { (*self.key).hash(state) }
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyRef<K> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { (*self.key).eq(&*other.key) }
+    }
+}
+
+impl<K: Eq> Eq for KeyRef<K> {}
+
+/// Обёртка для поиска в карте по заимствованной форме ключа `Q`
+#[repr(transparent)]
+struct KeyWrapper<Q: ?Sized>(Q);
+
+impl<Q: ?Sized> KeyWrapper<Q> {
+    fn from_ref(key: &Q) -> &Self {
+        // repr(transparent) гарантирует одинаковое представление `Q` и `KeyWrapper<Q>`
+        unsafe { &*(key as *const Q as *const KeyWrapper<Q>) }
+    }
+}
+
+impl<Q: ?Sized + Hash> Hash for KeyWrapper<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<Q: ?Sized + PartialEq> PartialEq for KeyWrapper<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+
+impl<Q: ?Sized + Eq> Eq for KeyWrapper<Q> {}
+
+impl<K, Q> Borrow<KeyWrapper<Q>> for KeyRef<K>
+where
+    K: Borrow<Q>,
+    Q: ?Sized,
+{
+    fn borrow(&self) -> &KeyWrapper<Q> {
+        KeyWrapper::from_ref(unsafe { (*self.key).borrow() })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CleanupMode {
     /// Автоматическая очистка при каждом доступе
     OnAccess,
-    /// Только при ручном вызове `evict_expired()`
+    /// Только при ручном вызове `evict_expired()`: срок проверяется лишь у запрошенного
+    /// ключа, что дешевле всего для чувствительных к задержкам сценариев
     OnDemand,
+    /// При каждом доступе удаляется не больше указанного числа просроченных элементов
+    /// (как активное истечение в Redis): стоимость операции ограничена, а память
+    /// элементов, к которым больше не обращаются, всё равно освобождается
+    Incremental(usize),
+}
+
+/// Оставшееся время жизни элемента, см. [`LruCache::ttl`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ttl {
+    /// Элемент без ограничения времени жизни
+    Persistent,
+    /// Время до истечения
+    Remaining(Duration),
+}
+
+/// Сведения об элементе для отладки вытеснения, см. [`LruCache::metadata`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMeta {
+    /// Время с записи текущего значения
+    pub age: Duration,
+    /// Время с последнего чтения (или записи, если чтений не было)
+    pub idle: Duration,
+    /// Число чтений с момента добавления ключа
+    pub access_count: u32,
+    pub ttl: Ttl,
 }
 
-pub struct LruCache<K, V> {
-    map: HashMap<K, NonNull<Node<K, V>>>,
+/// Вероятностное досрочное истечение (XFetch) против одновременного пересчёта, см.
+/// [`LruCache::set_early_expiration`]
+///
+/// `get` начинает промахиваться раньше срока: с вероятностью, растущей к концу TTL,
+/// поэтому обычно значение пересчитывает один вызывающий, пока остальные читают старое.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyExpiration {
+    /// Ожидаемое время пересчёта значения
+    pub delta: Duration,
+    /// Степень досрочности: 1.0 - оптимальное значение, больше - раньше
+    pub beta: f64,
+}
+
+/// Результат [`LruCache::get_stale`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup<T> {
+    /// Элемент не просрочен
+    Fresh(T),
+    /// Срок истёк, но не больше чем `stale_window` назад: значение можно отдать,
+    /// обновив его в фоне
+    Stale(T),
+    Miss,
+}
+
+/// Хешер по умолчанию: `ahash` при включённой опции `ahash`, иначе SipHash из std
+#[cfg(feature = "ahash")]
+pub type DefaultHashBuilder = ahash::RandomState;
+/// Хешер по умолчанию: `ahash` при включённой опции `ahash`, иначе SipHash из std
+#[cfg(not(feature = "ahash"))]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+
+pub struct LruCache<K, V, S = DefaultHashBuilder> {
+    map: HashMap<KeyRef<K>, NonNull<Node<K, V>>, S>,
+    // узлы с ограниченным временем жизни, см. [`ExpiryIndex`]
+    expiry: ExpiryQueue<K, V>,
     head: Option<NonNull<Node<K, V>>>,
     tail: Option<NonNull<Node<K, V>>>,
     capacity: usize,
     cleanup_mode: CleanupMode,
+    // функция веса, часы и политика истечения разделяются с копиями кеша, см. `Clone`
+    weigher: Option<Arc<dyn Weigher<K, V> + Send + Sync>>,
+    clock: Arc<dyn Clock + Send + Sync>,
+    expiry_policy: Option<Arc<dyn Expiry<K, V> + Send + Sync>>,
+    refresh: Option<RefreshAhead<K, V>>,
+    pending_refresh: Vec<NonNull<Node<K, V>>>,
+    listener: Option<Box<dyn RemovalListener<K, V> + Send + Sync>>,
+    // `Sync` обязателен: `on_miss` вызывается и под разделяемой блокировкой, см. `BufferedCache`
+    hooks: Option<Box<dyn AccessHooks<K> + Send + Sync>>,
+    stats: Stats,
+    // метка `cache` экспортируемых показателей, см. [`LruCache::publish_metrics`]
+    #[cfg(feature = "metrics")]
+    metrics_name: Option<String>,
+    // отметки отсутствующих ключей, см. [`LruCache::put_negative`]
+    negative: NegativeEntries<K>,
+    // теги элементов, см. [`LruCache::put_tagged`]
+    tags: TagIndex<K>,
+    weight: u64,
+    max_entries: Option<usize>,
+    default_ttl: Option<Duration>,
+    stale_window: Duration,
+    early_expiration: Option<EarlyExpiration>,
+    ttl_jitter: f64,
+    jitter_state: u64,
+    // элементы, перехваченные для следующего уровня хранения вместо передачи слушателю,
+    // см. [`LruCache::capture_evictions`]
+    demoted: Option<Demoted<K, V>>,
+    // значение, вставленное через `entry` в отключённый кеш (ёмкость 0):
+    // ссылку на него нужно вернуть, хотя сам кеш ничего не хранит
+    scratch: Option<V>,
 }
 
-impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+impl<K: Eq + Hash, V> LruCache<K, V> {
+    /// Создание кеша указанной ёмкости; ёмкость 0 отключает кеширование, см. [`LruCache::disabled`]
     pub fn new(capacity: usize, cleanup_mode: CleanupMode) -> Self {
-        assert!(capacity > 0);
+        Self::with_hasher(capacity, cleanup_mode, DefaultHashBuilder::default())
+    }
+
+    /// Построитель кеша с дополнительными параметрами, см. [`LruCacheBuilder`]
+    pub fn builder() -> LruCacheBuilder<K, V> {
+        LruCacheBuilder::new()
+    }
+
+    /// Отключённый кеш: `put` ничего не сохраняет, а поиск всегда промахивается
+    ///
+    /// Позволяет выключать кеширование конфигурацией без `Option<LruCache>`.
+    pub fn disabled() -> Self {
+        Self::new(0, CleanupMode::OnDemand)
+    }
+
+    /// Кеш, заранее заполненный элементами `(key, value, ttl)` в порядке от недавно
+    /// использованных к давно использованным (как в [`LruCache::iter`]), чтобы сервис
+    /// не начинал работу с пустым кешем
+    ///
+    /// Загрузка выполняется одной пакетной вставкой, см. [`LruCache::put_all`]; если элементов
+    /// больше ёмкости, остаются первые из них.
+    pub fn warm<I>(capacity: usize, entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V, Option<Duration>)>,
+    {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.reverse();
+        let mut cache = Self::new(capacity, CleanupMode::OnAccess);
+        cache.put_all(entries);
+        cache
+    }
+
+    /// Создание кеша без ограничения ёмкости: элементы покидают его только по истечении
+    /// времени жизни или при явном удалении, порядок LRU при этом сохраняется
+    ///
+    /// `capacity()` такого кеша возвращает `usize::MAX`.
+    pub fn unbounded(cleanup_mode: CleanupMode) -> Self {
+        Self::from_map(HashMap::default(), usize::MAX, cleanup_mode)
+    }
+
+    /// Создание кеша, ёмкость которого задаётся суммарным весом элементов, см. [`Weigher`]
+    pub fn with_weigher<W>(max_weight: usize, cleanup_mode: CleanupMode, weigher: W) -> Self
+    where
+        W: Weigher<K, V> + Send + Sync + 'static,
+    {
+        let mut cache = Self::from_map(HashMap::default(), max_weight, cleanup_mode);
+        cache.weigher = Some(Arc::new(weigher));
+        cache
+    }
+
+    /// Создание кеша с одновременными ограничениями на число элементов и суммарный вес
+    ///
+    /// Вытеснение происходит при превышении любого из ограничений; нулевое
+    /// ограничение отключает кеширование.
+    pub fn with_limits<W>(
+        max_entries: usize,
+        max_weight: usize,
+        cleanup_mode: CleanupMode,
+        weigher: W,
+    ) -> Self
+    where
+        W: Weigher<K, V> + Send + Sync + 'static,
+    {
+        let mut cache = Self::with_weigher(max_weight, cleanup_mode, weigher);
+        cache.max_entries = Some(max_entries);
+        cache
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Создание кеша с пользовательским хешером (`ahash`, `fxhash`, детерминированный для тестов)
+    pub fn with_hasher(capacity: usize, cleanup_mode: CleanupMode, hasher: S) -> Self {
+        let map = HashMap::with_capacity_and_hasher(capacity, hasher);
+        Self::from_map(map, capacity, cleanup_mode)
+    }
+
+    fn from_map(
+        map: HashMap<KeyRef<K>, NonNull<Node<K, V>>, S>,
+        capacity: usize,
+        cleanup_mode: CleanupMode,
+    ) -> Self {
         LruCache {
-            map: HashMap::with_capacity(capacity),
+            map,
+            expiry: ExpiryQueue::new(ExpiryIndex::Ordered, Instant::now()),
             head: None,
             tail: None,
             capacity,
             cleanup_mode,
+            weigher: None,
+            clock: clock::default_clock(),
+            expiry_policy: None,
+            refresh: None,
+            pending_refresh: Vec::new(),
+            listener: None,
+            hooks: None,
+            stats: Stats::new(stats::DEFAULT_HALF_LIFE, Instant::now()),
+            #[cfg(feature = "metrics")]
+            metrics_name: None,
+            negative: NegativeEntries::new(capacity),
+            tags: TagIndex::new(),
+            weight: 0,
+            max_entries: None,
+            default_ttl: None,
+            stale_window: Duration::ZERO,
+            early_expiration: None,
+            ttl_jitter: 0.0,
+            jitter_state: 0,
+            demoted: None,
+            scratch: None,
         }
     }
 
-    pub fn put(&mut self, key: K, value: V, ttl: Option<Duration>) {
-        if matches!(self.cleanup_mode, CleanupMode::OnAccess) {
-            self.evict_expired();
+    /// Кеш с нулевой ёмкостью или нулевым ограничением на число элементов ничего не хранит
+    pub fn is_disabled(&self) -> bool {
+        self.capacity == 0 || self.max_entries == Some(0)
+    }
+
+    /// Текущее время по часам кеша, см. [`Clock`]
+    fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn cleanup_mode(&self) -> CleanupMode {
+        self.cleanup_mode
+    }
+
+    /// Хешер, используемый кешем
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Добавляет элемент и возвращает прежнее значение, если оно было и не просрочено
+    ///
+    /// При `ttl = None` используется TTL кеша по умолчанию, если он задан;
+    /// `Some(Duration::MAX)` сохраняет элемент без ограничения времени.
+    pub fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        let now = self.now();
+        let expires_at = self.expires_at_for(&key, &value, ttl, now);
+        self.put_returning(key, value, expires_at, now)
+    }
+
+    /// Момент истечения вставляемого элемента: по `ttl`, а без него по [`Expiry`]
+    /// или TTL кеша по умолчанию
+    fn expires_at_for(
+        &mut self,
+        key: &K,
+        value: &V,
+        ttl: Option<Duration>,
+        now: Instant,
+    ) -> Option<Instant> {
+        match ttl {
+            None if self.expiry_policy.is_some() => self.policy_expires_at(key, value, now),
+            _ => self.expires_at(ttl, now),
         }
-        let expires_at = ttl.map(|d| Instant::now() + d);
+    }
 
-        if let Some(&node_ptr) = self.map.get(&key) {
-            unsafe {
-                let node = node_ptr.as_ptr().as_mut().unwrap();
-                node.value = value;
-                node.expires_at = expires_at;
-                self.remove_node(node_ptr);
-                self.push_front(node_ptr);
+    /// Момент истечения по [`Expiry`]: после создания или, если ключ уже есть, после перезаписи
+    fn policy_expires_at(&self, key: &K, value: &V, now: Instant) -> Option<Instant> {
+        let policy = self.expiry_policy.as_ref()?;
+
+        let ttl = match self.map.get(KeyWrapper::from_ref(key)) {
+            Some(&node_ptr) if unsafe { !(*node_ptr.as_ptr()).expired_at(now) } => {
+                let remaining = unsafe { (*node_ptr.as_ptr()).expires_at }
+                    .map(|expires_at| expires_at.saturating_duration_since(now));
+                policy.expire_after_update(key, value, now, remaining)
             }
+            _ => policy.expire_after_create(key, value, now),
+        };
+        now.checked_add(ttl?)
+    }
+
+    /// Продление или сокращение времени жизни прочитанного элемента по [`Expiry`]
+    fn on_read(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        let Some(policy) = self.expiry_policy.as_ref() else {
             return;
-        }
+        };
+
+        let (current, updated) = unsafe {
+            let node = &*node_ptr.as_ptr();
+            let remaining = node
+                .expires_at
+                .map(|expires_at| expires_at.saturating_duration_since(now));
+            let ttl = policy.expire_after_read(&node.key, &node.value, now, remaining);
+            (remaining, ttl)
+        };
 
-        if self.map.len() >= self.capacity {
-            self.remove_last();
+        if updated != current {
+            self.set_expiry(node_ptr, updated.and_then(|ttl| now.checked_add(ttl)));
         }
+    }
 
-        let node = Box::new(Node {
-            key: key.clone(),
-            value,
-            expires_at,
-            next: self.head,
-            prev: None,
-        });
+    /// Добавляет элемент, истекающий в момент `deadline` по системным часам
+    ///
+    /// Удобно для сроков, полученных извне (например, `exp` в токене). Момент в прошлом
+    /// делает элемент сразу просроченным; разброс TTL к абсолютному сроку не применяется.
+    /// Срок переводится в часы кеша по системному времени в момент вызова.
+    pub fn put_until(&mut self, key: K, value: V, deadline: SystemTime) -> Option<V> {
+        let ttl = deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO);
+        let now = self.now();
+        self.put_returning(key, value, now.checked_add(ttl), now)
+    }
 
-        let node_ptr = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
+    /// Вставка или перезапись; возвращает переданный ключ с прежним непросроченным значением
+    fn put_expiring(
+        &mut self,
+        key: K,
+        value: V,
+        expires_at: Option<Instant>,
+        now: Instant,
+    ) -> Option<(K, V)> {
+        if self.is_disabled() {
+            return None;
+        }
+        self.cleanup_on_access(now);
 
-        if let Some(mut head) = self.head {
-            unsafe { head.as_mut().prev = Some(node_ptr) };
-        } else {
-            self.tail = Some(node_ptr);
+        if let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(&key)) {
+            let (expired, old) = self.replace_value(node_ptr, value, expires_at, now);
+            self.evict_overflow(node_ptr, now);
+            if expired {
+                self.notify(key, old, RemovalCause::Expired);
+                return None;
+            }
+            return Some((key, old));
         }
 
-        self.head = Some(node_ptr);
-        self.map.insert(key, node_ptr);
+        let node_ptr = self.insert_new(key, value, expires_at, now);
+        self.evict_overflow(node_ptr, now);
+        None
     }
 
-    pub fn get(&mut self, key: &K) -> Option<&V> {
-        if matches!(self.cleanup_mode, CleanupMode::OnAccess) {
-            self.evict_expired();
-        }
+    /// [`LruCache::put_expiring`] с возвратом прежнего значения вызывающему,
+    /// слушатель получает его через [`RemovalListener::on_returned`]
+    fn put_returning(
+        &mut self,
+        key: K,
+        value: V,
+        expires_at: Option<Instant>,
+        now: Instant,
+    ) -> Option<V> {
+        let (key, old) = self.put_expiring(key, value, expires_at, now)?;
+        self.notify_returned(&key, &old, RemovalCause::Replaced);
+        Some(old)
+    }
 
-        // разименовать выгоднее, в противном случае необходим cloned notnull
-        let node_ptr = *self.map.get(key)?;
+    /// Перезапись значения узла с переносом в начало очереди, вытеснение выполняет вызывающий
+    ///
+    /// Возвращает прежнее значение и признак того, что оно было просрочено.
+    fn replace_value(
+        &mut self,
+        node_ptr: NonNull<Node<K, V>>,
+        value: V,
+        expires_at: Option<Instant>,
+        now: Instant,
+    ) -> (bool, V) {
+        let replaced = unsafe {
+            let node = &mut *node_ptr.as_ptr();
+            let weight = self.weigh(&node.key, &value);
+            self.weight = self.weight - node.weight as u64 + weight as u64;
+            node.weight = weight;
+            node.inserted_at = now;
+            (
+                node.expired_at(now),
+                std::mem::replace(&mut node.value, value),
+            )
+        };
 
-        unsafe {
-            let node = node_ptr.as_ptr().as_ref().unwrap();
+        self.set_expiry(node_ptr, expires_at);
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+        replaced
+    }
 
-            if node.expired() {
-                self.map.remove(key);
-                self.remove_node(node_ptr);
-                let _ = Box::from_raw(node_ptr.as_ptr());
-                return None;
+    /// Пакетная вставка элементов `(key, value, ttl)`, например при прогреве кеша
+    ///
+    /// Очистка просроченных и вытеснение выполняются один раз на весь пакет, место в карте
+    /// резервируется заранее. До вытеснения кеш временно хранит все элементы пакета,
+    /// а затем, как и при поочерёдных `put`, вытесняются наименее используемые.
+    /// Перезаписанные значения передаются слушателю с причиной [`RemovalCause::Replaced`].
+    pub fn put_all<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (K, V, Option<Duration>)>,
+    {
+        if self.is_disabled() {
+            return;
+        }
+        let entries = entries.into_iter();
+        self.map.reserve(entries.size_hint().0);
+        let now = self.now();
+        self.cleanup_on_access(now);
+
+        for (key, value, ttl) in entries {
+            let expires_at = self.expires_at_for(&key, &value, ttl, now);
+            match self.map.get(KeyWrapper::from_ref(&key)) {
+                Some(&node_ptr) => {
+                    let (expired, old) = self.replace_value(node_ptr, value, expires_at, now);
+                    let cause = if expired {
+                        RemovalCause::Expired
+                    } else {
+                        RemovalCause::Replaced
+                    };
+                    self.notify(key, old, cause);
+                }
+                None => {
+                    self.insert_new(key, value, expires_at, now);
+                }
             }
+        }
 
-            self.remove_node(node_ptr);
-            self.push_front(node_ptr);
+        while self.overflowed() && self.evict_last(now) {}
+    }
 
-            Some(&(*node_ptr.as_ptr()).value)
-        }
+    /// Копии непросроченных элементов `(key, value, ttl)` от недавно использованных к давно
+    /// использованным с оставшимся временем жизни (`None` - без ограничения)
+    ///
+    /// Вместе с [`LruCache::import`] позволяет перенести кеш между процессами
+    /// в любом формате, например по сокету при поэтапном перезапуске.
+    pub fn export(&self) -> Vec<(K, V, Option<Duration>)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.snapshot_entries()
+            .into_iter()
+            .map(|(key, value, ttl)| (key.clone(), value.clone(), ttl))
+            .collect()
     }
 
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        if matches!(self.cleanup_mode, CleanupMode::OnAccess) {
-            self.evict_expired();
+    /// Добавляет элементы в формате [`LruCache::export`], сохраняя их порядок и оставшиеся TTL
+    ///
+    /// В отличие от [`LruCache::put_all`], `None` означает отсутствие ограничения,
+    /// а не TTL кеша по умолчанию.
+    pub fn import<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (K, V, Option<Duration>)>,
+    {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.reverse();
+        self.put_all(
+            entries
+                .into_iter()
+                .map(|(key, value, ttl)| (key, value, Some(ttl.unwrap_or(Duration::MAX)))),
+        );
+    }
+
+    /// Переносит в кеш элементы `other`, например при сведении кешей рабочих потоков в общий
+    ///
+    /// При совпадении ключей остаётся значение, вставленное позже; проигравшее значение
+    /// получает слушатель его кеша с причиной [`RemovalCause::Replaced`]. Порядок LRU
+    /// объединяется по времени последнего доступа, затем лишние элементы вытесняются.
+    /// Моменты вставки и доступа сравниваются напрямую, поэтому кеши должны использовать общие часы.
+    /// Теги элементов `other` не переносятся.
+    pub fn merge<S2: BuildHasher>(&mut self, mut other: LruCache<K, V, S2>) {
+        if self.is_disabled() {
+            return;
         }
+        let now = self.now();
+        let other_now = other.now();
+        self.cleanup_on_access(now);
 
-        let node_ptr = *self.map.get(key)?;
+        // перенесённые узлы от недавно использованных к давно использованным,
+        // уже убранные из очереди, но учтённые в карте и весе
+        let mut arrived = Vec::with_capacity(other.len());
+        while let Some(head_ptr) = other.head {
+            let node = *other.unlink(head_ptr);
+            if node.expired_at(other_now) {
+                other.notify(node.key, node.value, RemovalCause::Expired);
+                continue;
+            }
 
-        unsafe {
-            let node = node_ptr.as_ptr().as_mut().unwrap();
+            // просроченный, но ещё не удалённый элемент уступает живому независимо от времени вставки
+            if let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(&node.key))
+                && unsafe { (*node_ptr.as_ptr()).expired_at(now) }
+            {
+                let expired = self.unlink(node_ptr);
+                self.notify(expired.key, expired.value, RemovalCause::Expired);
+            }
 
-            if node.expired() {
-                self.map.remove(key);
+            let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(&node.key)) else {
+                let node_ptr = self.insert_new(node.key, node.value, node.expires_at, now);
+                unsafe {
+                    let copy = &mut *node_ptr.as_ptr();
+                    copy.inserted_at = node.inserted_at;
+                    copy.accessed_at = node.accessed_at;
+                    copy.hits = node.hits;
+                }
                 self.remove_node(node_ptr);
-                let _ = Box::from_raw(node_ptr.as_ptr());
-                return None;
-            }
+                arrived.push(node_ptr);
+                continue;
+            };
 
+            let existing = unsafe { &mut *node_ptr.as_ptr() };
+            if node.inserted_at <= existing.inserted_at {
+                other.notify(node.key, node.value, RemovalCause::Replaced);
+                continue;
+            }
+            let weight = self.weigh(&existing.key, &node.value);
+            self.weight = self.weight - existing.weight as u64 + weight as u64;
+            existing.weight = weight;
+            existing.inserted_at = node.inserted_at;
+            existing.accessed_at = node.accessed_at;
+            existing.hits = node.hits;
+            let old = std::mem::replace(&mut existing.value, node.value);
+            self.set_expiry(node_ptr, node.expires_at);
             self.remove_node(node_ptr);
-            self.push_front(node_ptr);
+            arrived.push(node_ptr);
+            self.notify(node.key, old, RemovalCause::Replaced);
+        }
 
-            Some(&mut (*node_ptr.as_ptr()).value)
+        let mut own = Vec::with_capacity(self.map.len() - arrived.len());
+        let mut current = self.head.take();
+        self.tail = None;
+        while let Some(node_ptr) = current {
+            current = unsafe { (*node_ptr.as_ptr()).next };
+            own.push(node_ptr);
         }
-    }
 
-    fn remove_node(&mut self, node_ptr: NonNull<Node<K, V>>) {
-        unsafe {
-            let node = node_ptr.as_ptr();
+        // слияние двух упорядоченных очередей, вставка с конца в начало
+        let accessed_at =
+            |node_ptr: &NonNull<Node<K, V>>| unsafe { (*node_ptr.as_ptr()).accessed_at };
+        let (mut own, mut arrived) = (
+            own.into_iter().rev().peekable(),
+            arrived.into_iter().rev().peekable(),
+        );
+        loop {
+            let node_ptr = match (own.peek(), arrived.peek()) {
+                (Some(a), Some(b)) if accessed_at(a) <= accessed_at(b) => own.next(),
+                (Some(_), Some(_)) => arrived.next(),
+                (Some(_), None) => own.next(),
+                (None, _) => arrived.next(),
+            };
+            let Some(node_ptr) = node_ptr else {
+                break;
+            };
+            self.push_front(node_ptr);
+        }
 
-            if let Some(prev) = (*node).prev {
-                (*prev.as_ptr()).next = (*node).next;
-            } else {
-                self.head = (*node).next;
-            }
+        while self.overflowed() && self.evict_last(now) {}
+    }
 
-            if let Some(next) = (*node).next {
-                (*next.as_ptr()).prev = (*node).prev;
-            } else {
-                self.tail = (*node).prev;
+    /// Непросроченные элементы от недавно использованных к давно использованным
+    /// с оставшимся TTL (`None` - без ограничения)
+    fn snapshot_entries(&self) -> Vec<(&K, &V, Option<Duration>)> {
+        let now = self.now();
+        let mut entries = Vec::with_capacity(self.map.len());
+        let mut current = self.head;
+        while let Some(node_ptr) = current {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            current = node.next;
+            match node.ttl_at(now) {
+                Some(Ttl::Persistent) => entries.push((&node.key, &node.value, None)),
+                Some(Ttl::Remaining(ttl)) => entries.push((&node.key, &node.value, Some(ttl))),
+                None => {}
             }
         }
+        entries
     }
 
-    fn push_front(&mut self, node_ptr: NonNull<Node<K, V>>) {
-        unsafe {
-            (*node_ptr.as_ptr()).next = self.head;
-            (*node_ptr.as_ptr()).prev = None;
+    /// Добавляет элемент и возвращает пару, вытесненную ради освобождения места
+    ///
+    /// При обновлении существующего ключа ничего не вытесняется и возвращается `None`,
+    /// прежнее значение в этом случае возвращает [`LruCache::put`]. В режиме веса
+    /// может быть вытеснено несколько элементов, возвращается наименее используемый из них.
+    /// Отключённый кеш возвращает переданную пару, не сохраняя её.
+    pub fn push(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<(K, V)> {
+        if self.is_disabled() {
+            return Some((key, value));
+        }
+        let now = self.now();
+        self.cleanup_on_access(now);
 
-            if let Some(head) = self.head {
-                let head_mut = head.as_ptr() as *mut Node<K, V>;
-                (*head_mut).prev = Some(node_ptr);
-            } else {
-                self.tail = Some(node_ptr);
+        let expires_at = self.expires_at_for(&key, &value, ttl, now);
+        if self.map.contains_key(KeyWrapper::from_ref(&key)) {
+            if let Some((key, old)) = self.put_expiring(key, value, expires_at, now) {
+                self.notify(key, old, RemovalCause::Replaced);
             }
-
-            self.head = Some(node_ptr);
+            return None;
         }
-    }
 
-    fn remove_last(&mut self) {
-        if let Some(tail_ptr) = self.tail {
-            unsafe {
-                let key = &(*tail_ptr.as_ptr()).key;
-                let prev = (*tail_ptr.as_ptr()).prev;
-
-                self.map.remove(key);
+        let node_ptr = self.insert_new(key, value, expires_at, now);
+        let evicted = self.evict_overflow_returning(node_ptr, now)?;
+        self.stats.record_removal(RemovalCause::SizeEvicted);
+        Some((evicted.key, evicted.value))
+    }
 
-                match prev {
-                    Some(prev) => {
-                        let prev_mut = prev.as_ptr() as *mut Node<K, V>;
-                        (*prev_mut).next = None;
-                        self.tail = Some(prev);
-                    }
-                    None => {
-                        self.head = None;
-                        self.tail = None;
-                    }
-                }
+    /// Вставка отсутствующего ключа в начало очереди, вытеснение выполняет вызывающий
+    fn insert_new(
+        &mut self,
+        key: K,
+        value: V,
+        expires_at: Option<Instant>,
+        now: Instant,
+    ) -> NonNull<Node<K, V>> {
+        self.negative.remove(&key);
+        let weight = self.weigh(&key, &value);
+        self.weight += weight as u64;
 
-                let _ = Box::from_raw(tail_ptr.as_ptr());
-            }
+        let node_ptr = self.link_front(Box::new(Node {
+            key,
+            value,
+            weight,
+            refresh_queued: false,
+            expires_at: None,
+            timer: TimerHandle::DETACHED,
+            inserted_at: now,
+            accessed_at: now,
+            hits: 0,
+            next: None,
+            prev: None,
+        }));
+        self.set_expiry(node_ptr, expires_at);
+        self.stats.record_insertion();
+        if let Some(hooks) = self.hooks.as_ref() {
+            hooks.on_insert(unsafe { &(*node_ptr.as_ptr()).key });
         }
+        node_ptr
     }
 
-    pub fn evict_expired(&mut self) {
-        let now = Instant::now();
-        let mut current = self.head;
-
-        while let Some(node_ptr) = current {
-            unsafe {
-                let node = node_ptr.as_ptr();
-                current = (*node).next;
+    /// Размещает новый узел в начале очереди и в карте; вес и срок учитывает вызывающий
+    fn link_front(&mut self, mut node: Box<Node<K, V>>) -> NonNull<Node<K, V>> {
+        node.next = self.head;
+        let node_ptr = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
 
-                if (*node).expired_at(now) {
-                    self.map.remove(&(*node).key);
-                    self.remove_node(node_ptr);
-                    let _ = Box::from_raw(node);
-                }
-            }
+        if let Some(mut head) = self.head {
+            unsafe { head.as_mut().prev = Some(node_ptr) };
+        } else {
+            self.tail = Some(node_ptr);
         }
+
+        self.head = Some(node_ptr);
+        let key_ref = KeyRef {
+            key: unsafe { &raw const (*node_ptr.as_ptr()).key },
+        };
+        self.map.insert(key_ref, node_ptr);
+        node_ptr
     }
 
-    pub fn len(&self) -> usize {
-        self.map.len()
+    /// Меняет момент истечения узла, поддерживая индекс истечения
+    fn set_expiry(&mut self, node_ptr: NonNull<Node<K, V>>, expires_at: Option<Instant>) {
+        if let Some(old) = unsafe { (*node_ptr.as_ptr()).expires_at } {
+            self.expiry.remove(node_ptr, old);
+        }
+        if let Some(new) = expires_at {
+            self.expiry.insert(node_ptr, new);
+        }
+        unsafe { (*node_ptr.as_ptr()).expires_at = expires_at };
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.map.is_empty()
+    /// Момент истечения для `ttl` или, если он не задан, для TTL кеша по умолчанию
+    ///
+    /// Слишком большой срок (например, `Duration::MAX`) означает отсутствие ограничения.
+    fn expires_at(&mut self, ttl: Option<Duration>, now: Instant) -> Option<Instant> {
+        let ttl = ttl.or(self.default_ttl)?;
+        let ttl = if self.ttl_jitter > 0.0 && ttl != Duration::MAX {
+            ttl.mul_f64(1.0 + self.ttl_jitter * self.next_jitter())
+        } else {
+            ttl
+        };
+
+        now.checked_add(ttl)
     }
 
-    pub fn capacity(&self) -> usize {
-        self.capacity
+    /// Случайное число в диапазоне [-1, 1]
+    fn next_jitter(&mut self) -> f64 {
+        self.next_random() * 2.0 - 1.0
     }
-}
 
-impl<K, V> Drop for LruCache<K, V> {
-    fn drop(&mut self) {
-        let mut current = self.head;
-        while let Some(node_ptr) = current {
-            unsafe {
-                current = (*node_ptr.as_ptr()).next;
-                let _ = Box::from_raw(node_ptr.as_ptr());
-            }
+    /// Случайное число в диапазоне [0, 1) (xorshift64*, качества для разброса TTL достаточно)
+    fn next_random(&mut self) -> f64 {
+        if self.jitter_state == 0 {
+            self.jitter_state = RandomState::new().hash_one(self as *const Self) | 1;
         }
-    }
-}
 
-impl<K, V> Node<K, V> {
-    fn expired(&self) -> bool {
-        self.expires_at.map_or(false, |e| e <= Instant::now())
-    }
+        let mut x = self.jitter_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.jitter_state = x;
 
-    fn expired_at(&self, now: Instant) -> bool {
-        self.expires_at.map_or(false, |e| e <= now)
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::thread;
+    /// Досрочный промах по XFetch: элемент считается истёкшим, если
+    /// `now - delta * beta * ln(rand) >= expires_at`, вероятность растёт к концу TTL
+    fn expires_early(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) -> bool {
+        let Some(EarlyExpiration { delta, beta }) = self.early_expiration else {
+            return false;
+        };
+        let Some(expires_at) = (unsafe { (*node_ptr.as_ptr()).expires_at }) else {
+            return false;
+        };
 
-    #[test]
-    fn test_basic_operations() {
-        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
-        cache.put("a", 1, None);
-        cache.put("b", 2, None);
+        let gap = delta.mul_f64(-beta * (1.0 - self.next_random()).ln());
+        now.checked_add(gap).is_none_or(|early| early >= expires_at)
+    }
 
-        assert_eq!(cache.get(&"a"), Some(&1));
-        assert_eq!(cache.get(&"b"), Some(&2));
-        assert_eq!(cache.get(&"c"), None);
+    fn weigh(&self, key: &K, value: &V) -> u32 {
+        self.weigher
+            .as_ref()
+            .map_or(1, |weigher| weigher.weight(key, value))
+    }
 
-        cache.put("c", 3, None);
-        assert_eq!(cache.get(&"a"), None);
-        assert_eq!(cache.get(&"b"), Some(&2));
-        assert_eq!(cache.get(&"c"), Some(&3));
+    /// Пересчитывает вес значения, изменённого на месте, и вытесняет лишние элементы
+    fn reweigh(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        let node = unsafe { &mut *node_ptr.as_ptr() };
+        let weight = self.weigh(&node.key, &node.value);
+        if weight != node.weight {
+            self.weight = self.weight - node.weight as u64 + weight as u64;
+            node.weight = weight;
+            self.evict_overflow(node_ptr, now);
+        }
     }
 
-    #[test]
-    fn test_ttl_expiration_auto() {
-        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
-        cache.put("a", 1, Some(Duration::from_millis(150)));
-        cache.put("b", 2, None);
+    /// Вытесняет элементы с конца очереди, пока кеш превышает ёмкость
+    ///
+    /// Узел `keep` (только что вставленный) не вытесняется, поэтому элемент тяжелее
+    /// всей ёмкости остаётся в кеше один.
+    fn evict_overflow(&mut self, keep: NonNull<Node<K, V>>, now: Instant) {
+        if let Some(node) = self.evict_overflow_returning(keep, now) {
+            self.evict_node(*node);
+        }
+    }
 
-        assert_eq!(cache.get(&"a"), Some(&1));
-        assert_eq!(cache.get(&"b"), Some(&2));
+    /// То же, что `evict_overflow`, но наименее используемый из вытесненных
+    /// непросроченных элементов возвращается вместо передачи слушателю
+    fn evict_overflow_returning(
+        &mut self,
+        keep: NonNull<Node<K, V>>,
+        now: Instant,
+    ) -> Option<Box<Node<K, V>>> {
+        let mut evicted = None;
 
-        thread::sleep(Duration::from_millis(200));
+        while self.overflowed() {
+            match self.tail {
+                Some(tail_ptr) if tail_ptr != keep => {
+                    let node = self.unlink(tail_ptr);
+                    if node.expired_at(now) {
+                        self.notify(node.key, node.value, RemovalCause::Expired);
+                    } else if evicted.is_none() {
+                        evicted = Some(node);
+                    } else {
+                        self.evict_node(*node);
+                    }
+                }
+                _ => break,
+            }
+        }
 
-        assert_eq!(cache.get(&"a"), None);
-        assert_eq!(cache.get(&"b"), Some(&2));
+        evicted
     }
 
-    #[test]
-    fn test_ttl_expiration() {
-        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
-        cache.put("a", 1, Some(Duration::from_millis(150)));
-        cache.put("b", 2, None);
+    /// Вытеснение по ёмкости: элемент перехватывается, если это включено,
+    /// иначе передаётся слушателю
+    fn evict_node(&mut self, node: Node<K, V>) {
+        match self.demoted.as_mut() {
+            Some(demoted) => {
+                self.stats.record_removal(RemovalCause::SizeEvicted);
+                demoted
+                    .evicted
+                    .push((node.key, node.value, node.expires_at));
+            }
+            None => self.notify(node.key, node.value, RemovalCause::SizeEvicted),
+        }
+    }
 
-        assert_eq!(cache.get(&"a"), Some(&1));
-        assert_eq!(cache.get(&"b"), Some(&2));
+    /// Включает перехват вытесненных по ёмкости элементов для следующего уровня хранения:
+    /// слушатель их больше не получает, их забирает [`LruCache::take_evicted`]
+    pub(crate) fn capture_evictions(&mut self) {
+        self.demoted.get_or_insert_with(|| Demoted {
+            evicted: Vec::new(),
+            expired: None,
+        });
+    }
 
-        thread::sleep(Duration::from_millis(200));
+    /// Вдобавок перехватывает просроченные элементы, их забирает [`LruCache::take_demoted`]
+    pub(crate) fn capture_expirations(&mut self) {
+        self.capture_evictions();
+        if let Some(demoted) = self.demoted.as_mut() {
+            demoted.expired.get_or_insert_with(Vec::new);
+        }
+    }
 
-        cache.evict_expired();
+    /// Перехваченные вытесненные элементы с оставшимся TTL (`None` - без ограничения)
+    pub(crate) fn take_evicted(&mut self) -> Vec<(K, V, Option<Duration>)> {
+        let Some(demoted) = self.demoted.as_mut() else {
+            return Vec::new();
+        };
+        if demoted.evicted.is_empty() {
+            return Vec::new();
+        }
+        let now = self.clock.now();
+        std::mem::take(&mut demoted.evicted)
+            .into_iter()
+            .filter_map(|(key, value, expires_at)| match expires_at {
+                None => Some((key, value, None)),
+                Some(expires_at) if expires_at > now => Some((key, value, Some(expires_at - now))),
+                Some(_) => None,
+            })
+            .collect()
+    }
 
-        assert_eq!(cache.get(&"a"), None);
-        assert_eq!(cache.get(&"b"), Some(&2));
+    /// Все перехваченные элементы, вытесненные и просроченные, без сведений о TTL
+    pub(crate) fn take_demoted(&mut self) -> Vec<(K, V)> {
+        let Some(demoted) = self.demoted.as_mut() else {
+            return Vec::new();
+        };
+        let evicted = std::mem::take(&mut demoted.evicted);
+        let expired = demoted.expired.as_mut().map(std::mem::take);
+        evicted
+            .into_iter()
+            .map(|(key, value, _)| (key, value))
+            .chain(expired.into_iter().flatten())
+            .collect()
+    }
+
+    /// Учитывает удалённый элемент в статистике и передаёт слушателю, если он задан,
+    /// см. [`RemovalListener`]
+    fn notify(&mut self, key: K, value: V, cause: RemovalCause) {
+        self.stats.record_removal(cause);
+        if cause == RemovalCause::Expired
+            && let Some(expired) = self
+                .demoted
+                .as_mut()
+                .and_then(|demoted| demoted.expired.as_mut())
+        {
+            expired.push((key, value));
+            return;
+        }
+        if let Some(listener) = self.listener.as_ref() {
+            listener.on_removal(key, value, cause);
+        }
+    }
+
+    /// Учитывает элемент, значение которого возвращается вызывающему, см. [`RemovalListener::on_returned`]
+    fn notify_returned(&mut self, key: &K, value: &V, cause: RemovalCause) {
+        self.stats.record_removal(cause);
+        if let Some(listener) = self.listener.as_ref() {
+            listener.on_returned(key, value, cause);
+        }
+    }
+
+    /// Удаляет просроченный узел с уведомлением слушателя
+    fn unlink_expired(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        let node = self.unlink(node_ptr);
+        self.notify(node.key, node.value, RemovalCause::Expired);
+    }
+
+    fn overflowed(&self) -> bool {
+        self.weight > self.capacity as u64
+            || self.max_entries.is_some_and(|max| self.map.len() > max)
+    }
+
+    /// Удаляет узел из карты и списка, передавая владение вызывающему
+    fn unlink(&mut self, node_ptr: NonNull<Node<K, V>>) -> Box<Node<K, V>> {
+        unsafe {
+            self.map
+                .remove(KeyWrapper::from_ref(&(*node_ptr.as_ptr()).key));
+            self.tags.remove(&(*node_ptr.as_ptr()).key);
+            self.remove_node(node_ptr);
+            if let Some(expires_at) = (*node_ptr.as_ptr()).expires_at {
+                self.expiry.remove(node_ptr, expires_at);
+            }
+            if (*node_ptr.as_ptr()).refresh_queued {
+                self.pending_refresh.retain(|&pending| pending != node_ptr);
+            }
+
+            let node = Box::from_raw(node_ptr.as_ptr());
+            self.weight -= node.weight as u64;
+            node
+        }
+    }
+
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = self.read_node(key)?;
+
+        unsafe { Some(&(*node_ptr.as_ptr()).value) }
+    }
+
+    /// Как [`LruCache::get`], но вместе с хранимым ключом (как `HashMap::get_key_value`)
+    pub fn get_key_value<Q>(&mut self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = unsafe { &*self.read_node(key)?.as_ptr() };
+
+        Some((&node.key, &node.value))
+    }
+
+    /// Изменяемый доступ к значению; вес изменённого значения не пересчитывается,
+    /// поэтому в кеше с функцией веса используйте [`LruCache::get_guard`]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = self.read_node(key)?;
+
+        unsafe { Some(&mut (*node_ptr.as_ptr()).value) }
+    }
+
+    /// Изменяемый доступ к нескольким элементам сразу (как `HashMap::get_disjoint_mut`)
+    ///
+    /// Все элементы отмечаются как недавно использованные; упреждающее обновление
+    /// не выполняется, потому что загрузка могла бы вытеснить уже найденный элемент.
+    /// Паникует, если ключи повторяются.
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> [Option<&mut V>; N]
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        for (i, key) in keys.iter().enumerate() {
+            assert!(
+                !keys[..i].contains(key),
+                "ключи get_many_mut должны различаться"
+            );
+        }
+
+        let now = self.now();
+        self.cleanup_on_access(now);
+        let nodes = keys.map(|key| self.read_node_at(key, now));
+        for node_ptr in nodes.iter().flatten() {
+            self.on_read(*node_ptr, now);
+        }
+
+        nodes.map(|node_ptr| node_ptr.map(|node_ptr| unsafe { &mut (*node_ptr.as_ptr()).value }))
+    }
+
+    /// Чтение нескольких ключей за один проход: очистка просроченных выполняется один раз,
+    /// найденные элементы отмечаются как недавно использованные
+    ///
+    /// Возвращает только найденные пары в порядке ключей. Упреждающее обновление,
+    /// как и в [`LruCache::get_many_mut`], не выполняется.
+    pub fn get_batch<'q, Q, I>(&mut self, keys: I) -> Vec<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        let now = self.now();
+        self.cleanup_on_access(now);
+        let nodes: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| self.read_node_at(key, now))
+            .collect();
+        for &node_ptr in &nodes {
+            self.on_read(node_ptr, now);
+        }
+
+        nodes
+            .into_iter()
+            .map(|node_ptr| unsafe {
+                let node = &*node_ptr.as_ptr();
+                (&node.key, &node.value)
+            })
+            .collect()
+    }
+
+    /// Чтение для пакетных методов: без очистки, упреждающего обновления и продления по [`Expiry`]
+    ///
+    /// Найденный узел не просрочен на момент `now`, поэтому поиск следующих ключей
+    /// с тем же `now` его не удалит. `on_read` может сократить срок до `now`, поэтому
+    /// вызывающий применяет его только после поиска всех ключей.
+    fn read_node_at<Q>(&mut self, key: &Q, now: Instant) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(node_ptr) = self
+            .live_node(key, now)
+            .filter(|&node_ptr| !self.expires_early(node_ptr, now))
+        else {
+            self.record_miss(key, now);
+            return None;
+        };
+        self.record_hit(node_ptr, now);
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+        Some(node_ptr)
+    }
+
+    /// Чтение для `get`/`get_mut`: поиск с учётом досрочного истечения, упреждающее
+    /// обновление и перенос узла в начало очереди
+    fn read_node<Q>(&mut self, key: &Q) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        self.cleanup_on_access(now);
+
+        let Some(node_ptr) = self
+            .live_node(key, now)
+            .filter(|&node_ptr| !self.expires_early(node_ptr, now))
+        else {
+            self.record_miss(key, now);
+            return None;
+        };
+        self.record_hit(node_ptr, now);
+        self.refresh_ahead(node_ptr, now);
+        self.on_read(node_ptr, now);
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+        Some(node_ptr)
+    }
+
+    fn record_hit(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        self.stats.record_hit(now);
+        unsafe {
+            let node = &mut *node_ptr.as_ptr();
+            node.accessed_at = now;
+            node.hits = node.hits.saturating_add(1);
+        }
+        if let Some(hooks) = self.hooks.as_ref() {
+            hooks.on_hit(unsafe { &(*node_ptr.as_ptr()).key });
+        }
+    }
+
+    fn record_miss<Q>(&mut self, key: &Q, now: Instant)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.stats.record_miss(now);
+        if let Some(hooks) = self.hooks.as_ref() {
+            hooks.on_miss(MissedKey::new(&Borrowed(key)));
+        }
+    }
+
+    /// Узел ключа, если он есть и не просрочен; просроченный узел удаляется
+    fn live_node<Q>(&mut self, key: &Q, now: Instant) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // разименовать выгоднее, в противном случае необходим cloned notnull
+        let node_ptr = *self.map.get(KeyWrapper::from_ref(key))?;
+
+        if unsafe { (*node_ptr.as_ptr()).expired_at(now) } {
+            // в окне устаревания элемент ещё доступен через `get_stale`
+            if self.past_stale_window(node_ptr, now) {
+                self.unlink_expired(node_ptr);
+            }
+            return None;
+        }
+
+        Some(node_ptr)
+    }
+
+    fn past_stale_window(&self, node_ptr: NonNull<Node<K, V>>, now: Instant) -> bool {
+        unsafe { (*node_ptr.as_ptr()).expires_at }.is_some_and(|expires_at| {
+            expires_at
+                .checked_add(self.stale_window)
+                .is_none_or(|stale_until| stale_until <= now)
+        })
+    }
+
+    /// Чтение с учётом окна устаревания (stale-while-revalidate), см. [`LruCache::set_stale_window`]
+    ///
+    /// Просроченный элемент отдаётся как [`Lookup::Stale`], пока с момента истечения
+    /// прошло меньше `stale_window`; в обоих случаях элемент отмечается как недавно использованный.
+    pub fn get_stale<Q>(&mut self, key: &Q) -> Lookup<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        self.cleanup_on_access(now);
+
+        let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(key)) else {
+            self.record_miss(key, now);
+            return Lookup::Miss;
+        };
+
+        let stale = unsafe { (*node_ptr.as_ptr()).expired_at(now) };
+        if stale && self.past_stale_window(node_ptr, now) {
+            self.unlink_expired(node_ptr);
+            self.record_miss(key, now);
+            return Lookup::Miss;
+        }
+        self.record_hit(node_ptr, now);
+        if !stale {
+            self.on_read(node_ptr, now);
+        }
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+
+        let value = unsafe { &(*node_ptr.as_ptr()).value };
+        if stale {
+            Lookup::Stale(value)
+        } else {
+            Lookup::Fresh(value)
+        }
+    }
+
+    /// Отмечает элемент как недавно использованный без чтения значения
+    ///
+    /// В отличие от `get` не учитывается в статистике и хуках, не продлевает срок
+    /// через [`Expiry`] и не запускает упреждающее обновление.
+    pub fn touch<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        let Some(node_ptr) = self.live_node(key, now) else {
+            return false;
+        };
+
+        unsafe { (*node_ptr.as_ptr()).accessed_at = now };
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+        true
+    }
+
+    /// Перемещает элемент в начало очереди (недавно использованные)
+    pub fn promote<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(node_ptr) = self.live_node(key, self.now()) else {
+            return false;
+        };
+
+        self.remove_node(node_ptr);
+        self.push_front(node_ptr);
+        true
+    }
+
+    /// Перемещает элемент в конец очереди, делая его первым кандидатом на вытеснение
+    pub fn demote<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(node_ptr) = self.live_node(key, self.now()) else {
+            return false;
+        };
+
+        self.remove_node(node_ptr);
+        self.push_back(node_ptr);
+        true
+    }
+
+    /// Задаёт элементу новое время жизни, отсчитываемое от текущего момента,
+    /// не изменяя значение и порядок LRU
+    pub fn expire_after<Q>(&mut self, key: &Q, ttl: Duration) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        let Some(node_ptr) = self.live_node(key, now) else {
+            return false;
+        };
+
+        self.set_expiry(node_ptr, now.checked_add(ttl));
+        true
+    }
+
+    /// Снимает ограничение времени жизни (аналог `PERSIST` в Redis): элемент покинет кеш
+    /// только при вытеснении по LRU или явном удалении
+    pub fn persist<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(node_ptr) = self.live_node(key, self.now()) else {
+            return false;
+        };
+
+        self.set_expiry(node_ptr, None);
+        true
+    }
+
+    /// Доступ к элементу для вставки или изменения на месте, см. [`Entry`]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        let now = self.now();
+        self.cleanup_on_access(now);
+
+        if let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(&key)) {
+            if unsafe { !(*node_ptr.as_ptr()).expired_at(now) } {
+                self.record_hit(node_ptr, now);
+                self.remove_node(node_ptr);
+                self.push_front(node_ptr);
+                return Entry::Occupied(OccupiedEntry {
+                    cache: self,
+                    node_ptr,
+                });
+            }
+
+            self.unlink_expired(node_ptr);
+        }
+
+        self.record_miss(&key, now);
+        Entry::Vacant(VacantEntry { cache: self, key })
+    }
+
+    /// Возвращает элемент, а при его отсутствии вычисляет и сохраняет с TTL кеша по умолчанию
+    /// (или по [`Expiry`], если политика задана)
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &V {
+        self.get_or_put_with(key, f, None)
+    }
+
+    /// Аналог [`LruCache::get_or_insert_with`] с временем жизни для нового элемента
+    pub fn get_or_put_with<F: FnOnce() -> V>(&mut self, key: K, f: F, ttl: Option<Duration>) -> &V {
+        match self.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f(), ttl),
+        }
+    }
+
+    /// Аналог [`LruCache::get_or_insert_with`], где время жизни нового элемента
+    /// определяет само вычисление (например, по `max-age` из ответа сервера)
+    pub fn get_or_insert_with_ttl<F>(&mut self, key: K, f: F) -> &V
+    where
+        F: FnOnce() -> (V, Option<Duration>),
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let (value, ttl) = f();
+                entry.insert(value, ttl)
+            }
+        }
+    }
+
+    /// Аналог [`LruCache::get_or_insert_with`] для вычисления с ошибкой:
+    /// значение сохраняется только при `Ok` с TTL по умолчанию, ошибка возвращается вызывающему
+    pub fn get_or_try_insert_with<F, E>(&mut self, key: K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?, None)),
+        }
+    }
+
+    /// Получение элемента без изменения порядка LRU и без очистки просроченных
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = *self.map.get(KeyWrapper::from_ref(key))?;
+
+        unsafe {
+            let node = &*node_ptr.as_ptr();
+            if node.expired_at(self.now()) {
+                return None;
+            }
+
+            Some(&node.value)
+        }
+    }
+
+    /// Как [`LruCache::peek`], но вместе с хранимым ключом
+    pub fn peek_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = unsafe { &*self.map.get(KeyWrapper::from_ref(key))?.as_ptr() };
+        if node.expired_at(self.now()) {
+            return None;
+        }
+
+        Some((&node.key, &node.value))
+    }
+
+    /// Оставшееся время жизни элемента; `None`, если элемента нет или он просрочен
+    pub fn ttl<Q>(&self, key: &Q) -> Option<Ttl>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = *self.map.get(KeyWrapper::from_ref(key))?;
+        unsafe { (*node_ptr.as_ptr()).ttl_at(self.now()) }
+    }
+
+    /// Возраст, время простоя, число чтений и остаток TTL элемента без изменения порядка LRU
+    pub fn metadata<Q>(&self, key: &Q) -> Option<EntryMeta>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let ttl = self.ttl(key)?;
+        let node = unsafe { &*self.map.get(KeyWrapper::from_ref(key))?.as_ptr() };
+        let now = self.now();
+
+        Some(EntryMeta {
+            age: now.saturating_duration_since(node.inserted_at),
+            idle: now.saturating_duration_since(node.accessed_at),
+            access_count: node.hits,
+            ttl,
+        })
+    }
+
+    /// Проверка наличия элемента без изменения порядка LRU
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.peek(key).is_some()
+    }
+
+    /// Изменяемая ссылка на элемент без изменения порядка LRU
+    pub fn peek_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node_ptr = self.live_node(key, self.now())?;
+        unsafe { Some(&mut (*node_ptr.as_ptr()).value) }
+    }
+
+    /// Удаляет элемент из кеша и возвращает его значение
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        self.cleanup_on_access(now);
+        self.negative.remove(key);
+
+        let node_ptr = *self.map.get(KeyWrapper::from_ref(key))?;
+
+        let node = self.unlink(node_ptr);
+        if node.expired_at(now) {
+            self.notify(node.key, node.value, RemovalCause::Expired);
+            return None;
+        }
+
+        self.notify_returned(&node.key, &node.value, RemovalCause::Explicit);
+        Some(node.value)
+    }
+
+    /// Извлекает наименее используемый элемент
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let now = self.now();
+
+        while let Some(tail_ptr) = self.tail {
+            // просроченные элементы логически отсутствуют, пропускаем их
+            let node = self.unlink(tail_ptr);
+            if !node.expired_at(now) {
+                return Some((node.key, node.value));
+            }
+            self.notify(node.key, node.value, RemovalCause::Expired);
+        }
+
+        None
+    }
+
+    /// Извлекает последний элемент очереди вместе с причиной удаления
+    ///
+    /// В отличие от [`LruCache::pop_lru`] просроченные элементы не пропускаются,
+    /// а возвращаются с [`RemovalCause::Expired`]; остальные - с [`RemovalCause::Explicit`].
+    pub fn pop_lru_with_cause(&mut self) -> Option<(K, V, RemovalCause)> {
+        let tail_ptr = self.tail?;
+        let node = self.unlink(tail_ptr);
+        let cause = if node.expired_at(self.now()) {
+            RemovalCause::Expired
+        } else {
+            RemovalCause::Explicit
+        };
+        self.stats.record_removal(cause);
+        Some((node.key, node.value, cause))
+    }
+
+    fn remove_node(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        unsafe {
+            let node = node_ptr.as_ptr();
+
+            if let Some(prev) = (*node).prev {
+                (*prev.as_ptr()).next = (*node).next;
+            } else {
+                self.head = (*node).next;
+            }
+
+            if let Some(next) = (*node).next {
+                (*next.as_ptr()).prev = (*node).prev;
+            } else {
+                self.tail = (*node).prev;
+            }
+        }
+    }
+
+    fn push_front(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        unsafe {
+            (*node_ptr.as_ptr()).next = self.head;
+            (*node_ptr.as_ptr()).prev = None;
+
+            if let Some(head) = self.head {
+                let head_mut = head.as_ptr();
+                (*head_mut).prev = Some(node_ptr);
+            } else {
+                self.tail = Some(node_ptr);
+            }
+
+            self.head = Some(node_ptr);
+        }
+    }
+
+    fn push_back(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        unsafe {
+            (*node_ptr.as_ptr()).prev = self.tail;
+            (*node_ptr.as_ptr()).next = None;
+
+            if let Some(tail) = self.tail {
+                (*tail.as_ptr()).next = Some(node_ptr);
+            } else {
+                self.head = Some(node_ptr);
+            }
+
+            self.tail = Some(node_ptr);
+        }
+    }
+
+    /// Вытесняет последний элемент очереди, возвращает `false` для пустого кеша
+    fn evict_last(&mut self, now: Instant) -> bool {
+        let Some(tail_ptr) = self.tail else {
+            return false;
+        };
+
+        let node = self.unlink(tail_ptr);
+        let cause = if node.expired_at(now) {
+            RemovalCause::Expired
+        } else {
+            RemovalCause::SizeEvicted
+        };
+        if cause == RemovalCause::SizeEvicted {
+            self.evict_node(*node);
+        } else {
+            self.notify(node.key, node.value, cause);
+        }
+        true
+    }
+
+    /// Удаляет просроченные элементы за O(k log n), где k - число просроченных,
+    /// или за амортизированное O(k) с [`ExpiryIndex::TimerWheel`]
+    pub fn evict_expired(&mut self) {
+        self.evict_expired_limited(usize::MAX, self.now());
+    }
+
+    /// Итератор, извлекающий просроченные элементы в порядке истечения
+    ///
+    /// Каждый элемент возвращается с [`RemovalCause::Expired`] и слушателю не передаётся;
+    /// не извлечённые до сброса итератора элементы остаются в кеше.
+    pub fn drain_expired(&mut self) -> DrainExpired<'_, K, V, S> {
+        // элементы в окне устаревания ещё нужны `get_stale`
+        let now = self.now().checked_sub(self.stale_window);
+        DrainExpired { cache: self, now }
+    }
+
+    fn evict_expired_limited(&mut self, limit: usize, now: Instant) {
+        // элементы в окне устаревания ещё нужны `get_stale`
+        let Some(now) = now.checked_sub(self.stale_window) else {
+            return;
+        };
+
+        for _ in 0..limit {
+            let Some(node_ptr) = self.expiry.pop_expired(now) else {
+                break;
+            };
+            self.unlink_expired(node_ptr);
+        }
+    }
+
+    /// Очистка просроченных перед операцией в соответствии с [`CleanupMode`]
+    fn cleanup_on_access(&mut self, now: Instant) {
+        match self.cleanup_mode {
+            CleanupMode::OnAccess => self.evict_expired_limited(usize::MAX, now),
+            CleanupMode::Incremental(limit) => self.evict_expired_limited(limit, now),
+            CleanupMode::OnDemand => {}
+        }
+    }
+
+    /// Итератор по элементам от недавно использованных к давно использованным
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            current: self.head,
+            remaining: self.map.len(),
+            now: self.now(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Сравнение содержимого, как `==`, с дополнительным требованием одинакового порядка LRU
+    pub fn eq_ordered<S2: BuildHasher>(&self, other: &LruCache<K, V, S2>) -> bool
+    where
+        V: PartialEq,
+    {
+        self.iter().eq(other.iter())
+    }
+
+    /// Итератор по `(&K, &mut V)` в порядке LRU без изменения порядка
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            current: self.head,
+            remaining: self.map.len(),
+            now: self.now(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Итератор по ключам в порядке LRU
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Итератор по значениям в порядке LRU
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Итератор по изменяемым значениям в порядке LRU; порядок не меняется
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    /// Опустошает кеш, возвращая итератор по извлечённым `(K, V)` в порядке LRU
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        // список отсоединяется сразу, поэтому кеш остаётся пустым и корректным,
+        // даже если итератор будет сброшен до конца обхода
+        let now = self.now();
+        let remaining = self.map.len();
+        self.map.clear();
+        self.tags.clear();
+        self.expiry.clear();
+        self.pending_refresh.clear();
+        self.weight = 0;
+        let current = self.head.take();
+        self.tail = None;
+
+        Drain {
+            current,
+            remaining,
+            now,
+            listener: self.listener.as_deref(),
+            stats: &mut self.stats,
+            marker: PhantomData,
+        }
+    }
+
+    /// Оставляет только элементы, для которых предикат вернул `true`
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let now = self.now();
+        let mut current = self.head;
+
+        while let Some(node_ptr) = current {
+            unsafe {
+                let node = node_ptr.as_ptr();
+                current = (*node).next;
+
+                let cause = if (*node).expired_at(now) {
+                    RemovalCause::Expired
+                } else if !f(&(*node).key, &mut (*node).value) {
+                    RemovalCause::Explicit
+                } else {
+                    continue;
+                };
+                let node = self.unlink(node_ptr);
+                self.notify(node.key, node.value, cause);
+            }
+        }
+    }
+
+    /// Снимок счётчиков попаданий, промахов, вставок и удалений, см. [`CacheStats`]
+    pub fn stats(&self) -> CacheStats {
+        self.stats.snapshot(self.now())
+    }
+
+    /// Снимок статистики со сбросом счётчиков: каждый вызов возвращает прирост
+    /// с предыдущего, что удобно для периодического сбора метрик
+    pub fn stats_snapshot(&mut self) -> CacheStats {
+        let now = self.now();
+        self.stats.take(now)
+    }
+
+    /// Удаляет все элементы, сохраняя ёмкость
+    pub fn clear(&mut self) {
+        self.scratch = None;
+        self.negative.clear();
+        // сброшенный итератор освобождает узлы, уведомляя слушателя
+        drop(self.drain());
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Время жизни, применяемое при вставке без явного `ttl`
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl
+    }
+
+    /// Изменяет TTL по умолчанию; на уже сохранённые элементы не влияет
+    pub fn set_default_ttl(&mut self, default_ttl: Option<Duration>) {
+        self.default_ttl = default_ttl;
+    }
+
+    /// Окно устаревания: сколько просроченный элемент остаётся доступным через [`LruCache::get_stale`]
+    pub fn stale_window(&self) -> Duration {
+        self.stale_window
+    }
+
+    /// Задаёт окно устаревания; `Duration::ZERO` (по умолчанию) удаляет элементы сразу по истечении
+    pub fn set_stale_window(&mut self, stale_window: Duration) {
+        self.stale_window = stale_window;
+    }
+
+    pub fn early_expiration(&self) -> Option<EarlyExpiration> {
+        self.early_expiration
+    }
+
+    /// Включает досрочные промахи `get`/`get_mut` по XFetch; `None` отключает
+    pub fn set_early_expiration(&mut self, early_expiration: Option<EarlyExpiration>) {
+        assert!(early_expiration.is_none_or(|early| early.beta >= 0.0));
+        self.early_expiration = early_expiration;
+    }
+
+    /// Относительный разброс TTL: 0.1 означает случайное отклонение в пределах ±10%
+    pub fn ttl_jitter(&self) -> f64 {
+        self.ttl_jitter
+    }
+
+    /// Задаёт разброс TTL, чтобы элементы, вставленные одновременно, не истекали разом
+    pub fn set_ttl_jitter(&mut self, jitter: f64) {
+        assert!((0.0..=1.0).contains(&jitter));
+        self.ttl_jitter = jitter;
+    }
+
+    /// Ёмкость кеша: число элементов или суммарный вес в режиме [`Weigher`]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Ограничение на число элементов в режиме [`LruCache::with_limits`]
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Изменяет ограничение на число элементов, вытесняя лишние; `Some(0)` отключает кеширование
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+
+        if self.overflowed() {
+            self.evict_expired();
+        }
+        let now = self.now();
+        while self.overflowed() && self.evict_last(now) {}
+    }
+
+    /// Суммарный вес элементов; без [`Weigher`] совпадает с `len()`
+    pub fn total_weight(&self) -> u64 {
+        self.weight
+    }
+
+    /// Резервирует место под `additional` новых элементов, но не больше свободной ёмкости
+    ///
+    /// Узлы выделяются по одному при вставке, поэтому заранее резервируется только карта.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.weigher.is_some() {
+            self.map.reserve(additional);
+            return;
+        }
+
+        let free = self.capacity.saturating_sub(self.map.len());
+        self.map.reserve(additional.min(free));
+    }
+
+    /// Освобождает неиспользуемую память внутренней карты
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit();
+    }
+
+    /// Изменяет ёмкость; при уменьшении вытесняет сначала просроченные,
+    /// затем наименее используемые элементы
+    ///
+    /// Ёмкость отметок [`LruCache::put_negative`] меняется вместе с ней, если она
+    /// не задана явно через [`LruCacheBuilder::negative_capacity`].
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if !self.negative.fixed {
+            self.negative.set_capacity(capacity);
+        }
+
+        if self.overflowed() {
+            self.evict_expired();
+        }
+        let now = self.now();
+        while self.overflowed() && self.evict_last(now) {}
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S> {
+    fn free_nodes(&mut self) {
+        let mut current = self.head.take();
+        self.tail = None;
+
+        while let Some(node_ptr) = current {
+            unsafe {
+                current = (*node_ptr.as_ptr()).next;
+                let _ = Box::from_raw(node_ptr.as_ptr());
+            }
+        }
+    }
+}
+
+// Кеш единолично владеет узлами, указатели на них не покидают его пределов,
+// поэтому передача кеша в другой поток равносильна передаче ключей, значений и хешера
+unsafe impl<K: Send, V: Send, S: Send> Send for LruCache<K, V, S> {}
+// Методы `&self` читают узлы и часы, а промах буферизованного чтения (`BufferedCache`)
+// вызывает хуки под разделяемой блокировкой; все обратные вызовы хранятся
+// как `dyn ... + Send + Sync`, поэтому их одновременный вызов из нескольких потоков безопасен,
+// и общий доступ к кешу равносилен общему доступу к ключам, значениям и хешеру
+unsafe impl<K: Sync, V: Sync, S: Sync> Sync for LruCache<K, V, S> {}
+
+/// Глубокая копия: элементы копируются в том же порядке LRU с теми же сроками истечения
+/// и метаданными, вместе с параметрами кеша и статистикой
+///
+/// Функция веса, часы и политика истечения разделяются с исходным кешем. Слушатель удалений,
+/// хуки, упреждающее обновление и имя метрик не копируются, поэтому копию можно
+/// изменять и анализировать, не затрагивая наблюдателей исходного кеша.
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher + Clone> Clone for LruCache<K, V, S> {
+    fn clone(&self) -> Self {
+        let map = HashMap::with_capacity_and_hasher(self.map.len(), self.map.hasher().clone());
+        let mut cache = Self::from_map(map, self.capacity, self.cleanup_mode);
+        cache.expiry = ExpiryQueue::new(self.expiry.index(), self.now());
+        cache.weigher = self.weigher.clone();
+        cache.clock = self.clock.clone();
+        cache.expiry_policy = self.expiry_policy.clone();
+        cache.stats = self.stats.clone();
+        cache.negative = self.negative.clone();
+        cache.tags = self.tags.clone();
+        cache.max_entries = self.max_entries;
+        cache.default_ttl = self.default_ttl;
+        cache.stale_window = self.stale_window;
+        cache.early_expiration = self.early_expiration;
+        cache.ttl_jitter = self.ttl_jitter;
+
+        // с конца очереди, чтобы каждый следующий узел вставлялся в начало
+        let mut current = self.tail;
+        while let Some(node_ptr) = current {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            current = node.prev;
+            cache.weight += node.weight as u64;
+            let copy = cache.link_front(Box::new(Node {
+                key: node.key.clone(),
+                value: node.value.clone(),
+                weight: node.weight,
+                refresh_queued: false,
+                expires_at: None,
+                timer: TimerHandle::DETACHED,
+                inserted_at: node.inserted_at,
+                accessed_at: node.accessed_at,
+                hits: node.hits,
+                next: None,
+                prev: None,
+            }));
+            cache.set_expiry(copy, node.expires_at);
+        }
+        cache
+    }
+}
+
+/// Элементы от недавно использованных к давно использованным в виде `(ключ, значение, Ttl)`;
+/// просроченные, но ещё не удалённые элементы пропускаются, как в [`LruCache::iter`]
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for LruCache<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let now = self.clock.now();
+        let mut entries = Vec::new();
+        let mut current = self.head;
+        while let Some(node_ptr) = current {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            current = node.next;
+            if let Some(ttl) = node.ttl_at(now) {
+                entries.push((&node.key, &node.value, ttl));
+            }
+        }
+
+        f.debug_struct("LruCache")
+            .field("len", &self.map.len())
+            .field("capacity", &self.capacity)
+            .field("entries", &entries)
+            .finish()
+    }
+}
+
+/// Кеши равны, если содержат одинаковые непросроченные пары ключ-значение;
+/// порядок LRU, сроки и параметры не сравниваются, см. [`LruCache::eq_ordered`]
+impl<K, V, S, S2> PartialEq<LruCache<K, V, S2>> for LruCache<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &LruCache<K, V, S2>) -> bool {
+        self.iter().count() == other.iter().count()
+            && self
+                .iter()
+                .all(|(key, value)| other.peek(key) == Some(value))
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, S: BuildHasher> Eq for LruCache<K, V, S> {}
+
+impl<K, V, S> Drop for LruCache<K, V, S> {
+    fn drop(&mut self) {
+        self.free_nodes();
+    }
+}
+
+/// Итератор по `(&K, &V)` в порядке LRU, пропускающий просроченные элементы
+pub struct Iter<'a, K, V> {
+    current: Option<NonNull<Node<K, V>>>,
+    remaining: usize,
+    now: Instant,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// итераторы ведут себя как ссылки, которые они выдают: `(&K, &V)`, `(&K, &mut V)`
+// или владеющие `(K, V)`
+unsafe impl<K: Sync, V: Sync> Send for Iter<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Iter<'_, K, V> {}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node_ptr) = self.current {
+            unsafe {
+                let node = &*node_ptr.as_ptr();
+                self.current = node.next;
+                self.remaining -= 1;
+
+                if !node.expired_at(self.now) {
+                    return Some((&node.key, &node.value));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> IntoIterator for &'a LruCache<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Итератор по `(&K, &mut V)` в порядке LRU, пропускающий просроченные элементы
+pub struct IterMut<'a, K, V> {
+    current: Option<NonNull<Node<K, V>>>,
+    remaining: usize,
+    now: Instant,
+    marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+unsafe impl<K: Sync, V: Send> Send for IterMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for IterMut<'_, K, V> {}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node_ptr) = self.current {
+            unsafe {
+                let node = &mut *node_ptr.as_ptr();
+                self.current = node.next;
+                self.remaining -= 1;
+
+                if !node.expired_at(self.now) {
+                    return Some((&node.key, &mut node.value));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> IntoIterator for &'a mut LruCache<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Кеш из пар без TTL, ёмкость равна числу собранных пар (но не меньше 1), режим очистки
+/// [`CleanupMode::OnAccess`]
+///
+/// Для других параметров кеш создаётся построителем и заполняется через `extend`.
+impl<K: Eq + Hash, V, S: BuildHasher + Default> FromIterator<(K, V)> for LruCache<K, V, S> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let entries: Vec<_> = iter.into_iter().collect();
+        let mut cache =
+            Self::with_hasher(entries.len().max(1), CleanupMode::OnAccess, S::default());
+        cache.extend(entries);
+        cache
+    }
+}
+
+/// Кеш с ёмкостью по числу элементов карты, как при [`FromIterator`];
+/// порядок LRU определяется порядком обхода карты и потому произволен
+impl<K: Eq + Hash, V, S: BuildHasher + Default, H> From<HashMap<K, V, H>> for LruCache<K, V, S> {
+    fn from(map: HashMap<K, V, H>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// Пакетная вставка без явного TTL (действует TTL по умолчанию), см. [`LruCache::put_all`]
+impl<K: Eq + Hash, V, S: BuildHasher> Extend<(K, V)> for LruCache<K, V, S> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.put_all(iter.into_iter().map(|(key, value)| (key, value, None)));
+    }
+}
+
+/// Итератор по ключам в порядке LRU
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Итератор по значениям в порядке LRU
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Итератор по изменяемым значениям в порядке LRU
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Итератор по просроченным элементам, см. [`LruCache::drain_expired`]
+pub struct DrainExpired<'a, K, V, S = DefaultHashBuilder> {
+    cache: &'a mut LruCache<K, V, S>,
+    now: Option<Instant>,
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Iterator for DrainExpired<'_, K, V, S> {
+    type Item = (K, V, RemovalCause);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.cache.expiry.pop_expired(self.now?)?;
+        let node = self.cache.unlink(node_ptr);
+        self.cache.stats.record_removal(RemovalCause::Expired);
+        Some((node.key, node.value, RemovalCause::Expired))
+    }
+}
+
+/// Итератор, извлекающий элементы из кеша, см. [`LruCache::drain`]
+pub struct Drain<'a, K, V> {
+    current: Option<NonNull<Node<K, V>>>,
+    remaining: usize,
+    now: Instant,
+    listener: Option<&'a (dyn RemovalListener<K, V> + Send + Sync)>,
+    stats: &'a mut Stats,
+    marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+unsafe impl<K: Send, V: Send> Send for Drain<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Drain<'_, K, V> {}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node_ptr) = self.current {
+            let node = unsafe { Box::from_raw(node_ptr.as_ptr()) };
+            self.current = node.next;
+            self.remaining -= 1;
+
+            if !node.expired_at(self.now) {
+                return Some((node.key, node.value));
+            }
+            self.stats.record_removal(RemovalCause::Expired);
+            if let Some(listener) = self.listener {
+                listener.on_removal(node.key, node.value, RemovalCause::Expired);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<K, V> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        while let Some(node_ptr) = self.current {
+            let node = unsafe { Box::from_raw(node_ptr.as_ptr()) };
+            self.current = node.next;
+
+            let cause = if node.expired_at(self.now) {
+                RemovalCause::Expired
+            } else {
+                RemovalCause::Explicit
+            };
+            self.stats.record_removal(cause);
+            if let Some(listener) = self.listener {
+                listener.on_removal(node.key, node.value, cause);
+            }
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn expired_at(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|e| e <= now)
+    }
+
+    /// Оставшееся время жизни на момент `now`; `None` для просроченного узла
+    fn ttl_at(&self, now: Instant) -> Option<Ttl> {
+        match self.expires_at {
+            None => Some(Ttl::Persistent),
+            Some(expires_at) if expires_at > now => Some(Ttl::Remaining(expires_at - now)),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_basic_operations() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.get(&"c"), None);
+
+        cache.put("c", 3, None);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn test_ttl_expiration_auto() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, Some(Duration::from_millis(150)));
+        cache.put("b", 2, None);
+
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), Some(&2));
+
+        thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
+    }
+
+    #[test]
+    fn test_ttl_expiration() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put("a", 1, Some(Duration::from_millis(150)));
+        cache.put("b", 2, None);
+
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), Some(&2));
+
+        thread::sleep(Duration::from_millis(200));
+
+        cache.evict_expired();
+
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = LruCache::new(3, CleanupMode::OnAccess);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+
+        cache.get(&"a");
+        cache.put("d", 4, None);
+
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"c"), Some(&3));
+        assert_eq!(cache.get(&"d"), Some(&4));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LruCache<String, Vec<u8>>>();
+        assert_send_sync::<Iter<'_, String, Vec<u8>>>();
+        assert_send_sync::<IterMut<'_, String, Vec<u8>>>();
+        assert_send_sync::<Drain<'_, String, Vec<u8>>>();
+        assert_send_sync::<OccupiedEntry<'_, String, Vec<u8>>>();
+
+        // кеш перемещается в поток и разделяется между потоками под `Mutex`
+        let cache = std::sync::Arc::new(std::sync::Mutex::new(LruCache::new(
+            10,
+            CleanupMode::OnAccess,
+        )));
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let cache = cache.clone();
+                thread::spawn(move || cache.lock().unwrap().put(i, i.to_string(), None))
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(cache.lock().unwrap().len(), 4);
+
+        // общий доступ на чтение из нескольких потоков
+        let cache =
+            std::sync::Mutex::into_inner(std::sync::Arc::into_inner(cache).unwrap()).unwrap();
+        thread::scope(|scope| {
+            for i in 0..4 {
+                let cache = &cache;
+                scope.spawn(move || assert_eq!(cache.peek(&i), Some(&i.to_string())));
+            }
+        });
+    }
+
+    #[test]
+    fn test_no_memory_leaks() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        for i in 0..1000 {
+            cache.put(i, Box::new([0u8; 1024]), None);
+        }
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+
+        assert_eq!(cache.remove(&"a"), Some(1));
+        assert_eq!(cache.remove(&"a"), None);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.len(), 1);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.remove(&"b"), None);
+        assert!(cache.is_empty());
+
+        cache.put("c", 3, None);
+        cache.put("d", 4, None);
+        assert_eq!(cache.get(&"c"), Some(&3));
+        assert_eq!(cache.get(&"d"), Some(&4));
+    }
+
+    #[test]
+    fn test_pop_lru() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+        cache.get(&"a");
+
+        assert_eq!(cache.pop_lru(), Some(("b", 2)));
+        assert_eq!(cache.pop_lru(), Some(("c", 3)));
+        assert_eq!(cache.pop_lru(), Some(("a", 1)));
+        assert_eq!(cache.pop_lru(), None);
+        assert!(cache.is_empty());
+
+        cache.put("d", 4, Some(Duration::from_millis(50)));
+        cache.put("e", 5, None);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.pop_lru(), Some(("e", 5)));
+        assert_eq!(cache.pop_lru(), None);
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+
+        // peek не продвигает "a", поэтому он будет вытеснен первым
+        assert_eq!(cache.peek(&"a"), Some(&1));
+        assert_eq!(cache.peek(&"c"), None);
+        cache.get(&"b");
+        cache.put("c", 3, None);
+        assert_eq!(cache.peek(&"a"), None);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.peek(&"b"), None);
+        assert_eq!(cache.peek(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+
+        *cache.peek_mut(&"a").unwrap() += 10;
+        assert_eq!(cache.peek(&"a"), Some(&11));
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.peek_mut(&"b"), None);
+        assert_eq!(cache.len(), 1);
+
+        // "a" не продвигался и остаётся последним в очереди на вытеснение
+        cache.put("c", 3, None);
+        cache.put("d", 4, None);
+        assert_eq!(cache.peek(&"a"), None);
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+
+        cache.put("c", 3, None);
+        assert!(!cache.contains_key(&"a"));
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!cache.contains_key(&"b"));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.pop_lru(), None);
+
+        cache.put("c", 3, None);
+        cache.put("d", 4, None);
+        cache.put("e", 5, None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"d"), Some(&4));
+        assert_eq!(cache.get(&"e"), Some(&5));
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+        cache.put("c", 3, None);
+        cache.get(&"a");
+
+        let items: Vec<_> = cache.iter().collect();
+        assert_eq!(items, vec![(&"a", &1), (&"c", &3), (&"b", &2)]);
+
+        thread::sleep(Duration::from_millis(100));
+        let items: Vec<_> = (&cache).into_iter().collect();
+        assert_eq!(items, vec![(&"a", &1), (&"c", &3)]);
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+
+        for (_, value) in cache.iter_mut() {
+            *value *= 10;
+        }
+        for (_, value) in &mut cache {
+            *value += 1;
+        }
+
+        let items: Vec<_> = cache.iter().collect();
+        assert_eq!(items, vec![(&"c", &31), (&"b", &21), (&"a", &11)]);
+    }
+
+    #[test]
+    fn test_get_key_value() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        let key: std::sync::Arc<str> = "a".into();
+        cache.put(key.clone(), 1, None);
+        cache.put("b".into(), 2, None);
+
+        let (stored, _) = cache.peek_key_value("a").unwrap();
+        assert!(std::sync::Arc::ptr_eq(stored, &key));
+        assert_eq!(cache.keys().next().map(|key| &**key), Some("b"));
+
+        assert_eq!(cache.get_key_value("a").map(|(_, value)| *value), Some(1));
+        assert_eq!(cache.keys().next().map(|key| &**key), Some("a"));
+        assert!(cache.get_key_value("x").is_none());
+    }
+
+    #[test]
+    fn test_get_many_mut() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 10, None);
+        cache.put("b", 0, None);
+        cache.put("c", 0, None);
+
+        if let [Some(a), Some(b), None] = cache.get_many_mut(["a", "b", "x"]) {
+            std::mem::swap(a, b);
+        }
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"b", &"a", &"c"]);
+        assert_eq!(cache.peek("b"), Some(&10));
+
+        let duplicate = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cache.get_many_mut(["a", "a"]);
+        }));
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn test_put_all() {
+        let mut cache = LruCache::new(3, CleanupMode::OnAccess);
+        cache.put("a", 0, None);
+        cache.put_all([
+            ("a", 1, None),
+            ("b", 2, Some(Duration::from_secs(60))),
+            ("c", 3, None),
+            ("d", 4, None),
+        ]);
+
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"d", &"c", &"b"]);
+        assert!(matches!(cache.ttl(&"b"), Some(Ttl::Remaining(_))));
+        let stats = cache.stats();
+        assert_eq!((stats.insertions, stats.evictions), (4, 1));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.extend((1..=3).map(|i| (i, i * 10)));
+        assert_eq!(cache.iter().collect::<Vec<_>>(), vec![(&3, &30), (&2, &20)]);
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let cache: LruCache<_, _> = [("a", 1), ("b", 2), ("a", 3)].into_iter().collect();
+        assert_eq!(cache.capacity(), 3);
+        assert_eq!(
+            cache.iter().collect::<Vec<_>>(),
+            vec![(&"a", &3), (&"b", &2)]
+        );
+
+        let empty: LruCache<u32, u32> = std::iter::empty().collect();
+        assert_eq!(empty.capacity(), 1);
+    }
+
+    #[test]
+    fn test_from_hash_map() {
+        let map: HashMap<_, _> = (0..5).map(|i| (i, i * i)).collect();
+        let mut cache = LruCache::<_, _>::from(map);
+        assert_eq!((cache.len(), cache.capacity()), (5, 5));
+        assert_eq!(cache.get(&3), Some(&9));
+    }
+
+    #[test]
+    fn test_clone() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(3)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+        cache.put("a", 1, Some(Duration::from_secs(10)));
+        cache.put("b", 2, None);
+        cache.put("c", 3, Some(Duration::from_secs(30)));
+        cache.get(&"a");
+
+        let mut copy = cache.clone();
+        assert_eq!(
+            copy.iter().collect::<Vec<_>>(),
+            cache.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(copy.stats(), cache.stats());
+
+        // копия независима от исходного кеша, но сроки у них общие
+        copy.put("d", 4, None);
+        assert_eq!(cache.len(), 3);
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(copy.keys().collect::<Vec<_>>(), vec![&"d", &"c"]);
+        assert_eq!(
+            copy.ttl(&"c"),
+            Some(Ttl::Remaining(Duration::from_secs(10)))
+        );
+    }
+
+    #[test]
+    fn test_debug() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder().capacity(2).clock(clock.clone()).build();
+        cache.put("a", 1, Some(Duration::from_secs(5)));
+        cache.put("b", 2, None);
+        assert_eq!(
+            format!("{cache:?}"),
+            r#"LruCache { len: 2, capacity: 2, entries: [("b", 2, Persistent), ("a", 1, Remaining(5s))] }"#
+        );
+    }
+
+    #[test]
+    fn test_partial_eq() {
+        let mut a = LruCache::new(3, CleanupMode::OnAccess);
+        let mut b = LruCache::new(5, CleanupMode::OnAccess);
+        a.put(1, "x", None);
+        a.put(2, "y", None);
+        b.put(2, "y", None);
+        b.put(1, "x", None);
+        assert_eq!(a, b);
+        assert!(!a.eq_ordered(&b));
+
+        b.get(&2);
+        assert!(a.eq_ordered(&b));
+        b.put(3, "z", Some(Duration::ZERO));
+        assert_eq!(a, b);
+        b.put(1, "w", None);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_warm() {
+        let entries = [
+            ("a", 1, None),
+            ("b", 2, Some(Duration::from_secs(60))),
+            ("c", 3, None),
+        ];
+        let mut cache = LruCache::warm(2, entries);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a", &"b"]);
+        assert!(matches!(cache.ttl(&"b"), Some(Ttl::Remaining(_))));
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.get(&"a"), Some(&1));
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut source = LruCache::builder()
+            .capacity(3)
+            .default_ttl(Duration::from_secs(5))
+            .build();
+        source.put("a", 1, Some(Duration::MAX));
+        source.put("b", 2, None);
+        source.put("c", 3, Some(Duration::ZERO));
+
+        let exported = source.export();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[1], ("a", 1, None));
+
+        let mut target = LruCache::builder()
+            .capacity(3)
+            .default_ttl(Duration::from_secs(1))
+            .build();
+        target.import(exported);
+        assert!(target.eq_ordered(&source));
+        assert_eq!(target.ttl(&"a"), Some(Ttl::Persistent));
+        assert!(
+            matches!(target.ttl(&"b"), Some(Ttl::Remaining(ttl)) if ttl > Duration::from_secs(1))
+        );
     }
 
     #[test]
-    fn test_lru_eviction() {
-        let mut cache = LruCache::new(3, CleanupMode::OnAccess);
+    fn test_merge() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder().capacity(3).clock(clock.clone()).build();
+        let mut worker = LruCache::builder().capacity(3).clock(clock.clone()).build();
+
+        cache.put("a", 1, None);
+        clock.advance(Duration::from_secs(1));
+        worker.put("d", 4, None);
+        clock.advance(Duration::from_secs(1));
+        worker.put("a", 2, None);
+        worker.put("b", 20, None);
+        clock.advance(Duration::from_secs(1));
+        cache.put("b", 10, None);
+        clock.advance(Duration::from_secs(1));
+        cache.put("c", 3, Some(Duration::from_secs(5)));
+
+        // "a" вставлен позже в рабочем кеше, "b" - в общем; давно использованный "d" вытеснен
+        cache.merge(worker);
+        let entries: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, [("c", 3), ("b", 10), ("a", 2)]);
+        assert_eq!(cache.stats().evictions, 1);
+        assert!(matches!(cache.ttl(&"c"), Some(Ttl::Remaining(_))));
+
+        // более новое, но просроченное значение уступает живому
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+        let mut worker = LruCache::builder().capacity(2).clock(clock.clone()).build();
+        worker.put("k", 1, None);
+        clock.advance(Duration::from_secs(1));
+        cache.put("k", 2, Some(Duration::from_secs(1)));
+        clock.advance(Duration::from_secs(2));
+        cache.merge(worker);
+        assert_eq!(cache.get(&"k"), Some(&1));
+        assert_eq!(cache.stats().expirations, 1);
+    }
+
+    #[test]
+    fn test_get_batch() {
+        let mut cache = LruCache::new(4, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::from_millis(20)));
+        cache.put("c", 3, None);
+        cache.put("d", 4, None);
+        thread::sleep(Duration::from_millis(50));
+
+        let hits = cache.get_batch(["c", "b", "x", "a"]);
+        assert_eq!(hits, vec![(&"c", &3), (&"a", &1)]);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a", &"c", &"d"]);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+    }
+
+    #[test]
+    fn test_keys_values() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+        cache.get(&"b");
+
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"b", &"c", &"a"]);
+        assert_eq!(cache.values().collect::<Vec<_>>(), vec![&2, &3, &1]);
+
+        cache.values_mut().for_each(|value| *value *= 10);
+        assert_eq!(cache.values().collect::<Vec<_>>(), vec![&20, &30, &10]);
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+
+        assert_eq!(
+            cache.drain().collect::<Vec<_>>(),
+            vec![("c", 3), ("b", 2), ("a", 1)]
+        );
+        assert!(cache.is_empty());
+
+        cache.put("d", 4, None);
+        cache.put("e", 5, None);
+        {
+            let mut drain = cache.drain();
+            assert_eq!(drain.next(), Some(("e", 5)));
+        }
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&"d"), None);
+
+        cache.put("f", 6, None);
+        assert_eq!(cache.get(&"f"), Some(&6));
+    }
+
+    #[test]
+    fn test_removal_causes() {
+        let mut cache = LruCache::new(10, CleanupMode::OnDemand);
+        cache.put("a", 1, Some(Duration::from_millis(20)));
+        cache.put("b", 2, None);
+        cache.put("c", 3, Some(Duration::from_millis(10)));
+        cache.put("d", 4, None);
+        thread::sleep(Duration::from_millis(30));
+
+        let expired: Vec<_> = cache.drain_expired().collect();
+        assert_eq!(
+            expired,
+            vec![
+                ("c", 3, RemovalCause::Expired),
+                ("a", 1, RemovalCause::Expired)
+            ]
+        );
+
+        cache.put("e", 5, Some(Duration::from_millis(10)));
+        cache.get(&"b");
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(
+            cache.pop_lru_with_cause(),
+            Some(("d", 4, RemovalCause::Explicit))
+        );
+        assert_eq!(
+            cache.pop_lru_with_cause(),
+            Some(("e", 5, RemovalCause::Expired))
+        );
+        assert_eq!(
+            cache.pop_lru_with_cause(),
+            Some(("b", 2, RemovalCause::Explicit))
+        );
+        assert_eq!(cache.pop_lru_with_cause(), None);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut cache = LruCache::new(4, CleanupMode::OnDemand);
+        cache.put(("tenant1", 1), 1, None);
+        cache.put(("tenant2", 1), 2, None);
+        cache.put(("tenant1", 2), 3, None);
+        cache.put(("tenant2", 2), 4, None);
+
+        cache.retain(|&(tenant, _), _| tenant != "tenant1");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.keys().collect::<Vec<_>>(),
+            vec![&("tenant2", 2), &("tenant2", 1)]
+        );
+
+        cache.retain(|_, value| {
+            *value += 1;
+            true
+        });
+        assert_eq!(cache.values().collect::<Vec<_>>(), vec![&5, &3]);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        let mut calls = 0;
+
+        assert_eq!(
+            *cache.get_or_insert_with("a", || {
+                calls += 1;
+                1
+            }),
+            1
+        );
+        assert_eq!(
+            *cache.get_or_insert_with("a", || {
+                calls += 1;
+                2
+            }),
+            1
+        );
+        assert_eq!(calls, 1);
+
+        assert_eq!(
+            *cache.get_or_put_with("b", || 2, Some(Duration::from_millis(50))),
+            2
+        );
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(*cache.get_or_put_with("b", || 3, None), 3);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+
+        let result: Result<&i32, &str> = cache.get_or_try_insert_with("a", || Err("db error"));
+        assert_eq!(result, Err("db error"));
+        assert!(!cache.contains_key(&"a"));
+
+        let result: Result<&i32, &str> = cache.get_or_try_insert_with("a", || Ok(1));
+        assert_eq!(result, Ok(&1));
+
+        let result: Result<&i32, &str> = cache.get_or_try_insert_with("a", || Err("unused"));
+        assert_eq!(result, Ok(&1));
+    }
+
+    #[test]
+    fn test_get_or_insert_with_ttl() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+
+        let value = cache.get_or_insert_with_ttl("a", || (1, Some(Duration::from_millis(50))));
+        assert_eq!(*value, 1);
+        assert_eq!(*cache.get_or_insert_with_ttl("a", || (2, None)), 1);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(*cache.get_or_insert_with_ttl("a", || (3, None)), 3);
+
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get(&"a"), Some(&3));
+    }
+
+    #[test]
+    fn test_put_returns_previous() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+
+        assert_eq!(cache.put("a", 1, None), None);
+        assert_eq!(cache.put("a", 2, Some(Duration::from_millis(50))), Some(1));
+        assert_eq!(cache.get(&"a"), Some(&2));
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.put("a", 3, None), None);
+        assert_eq!(cache.get(&"a"), Some(&3));
+    }
+
+    #[test]
+    fn test_push_returns_evicted() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+
+        assert_eq!(cache.push("a", 1, None), None);
+        assert_eq!(cache.push("b", 2, None), None);
+        assert_eq!(cache.push("b", 3, None), None);
+        assert_eq!(cache.push("c", 4, None), Some(("a", 1)));
+        assert_eq!(cache.get(&"b"), Some(&3));
+
+        cache.put("d", 5, Some(Duration::from_millis(50)));
+        cache.get(&"b");
+        thread::sleep(Duration::from_millis(100));
+        // просроченный элемент вытесняется молча
+        assert_eq!(cache.push("e", 6, None), None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"e", &"b"]);
+    }
+
+    #[test]
+    fn test_touch() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+
+        assert!(cache.touch(&"a"));
+        assert!(cache.touch(&"a"));
+        assert!(!cache.touch(&"c"));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (0, 0));
+
+        cache.put("c", 3, None);
+        assert!(cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"b"));
+    }
+
+    #[test]
+    fn test_promote_demote() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
         cache.put("a", 1, None);
         cache.put("b", 2, None);
         cache.put("c", 3, None);
 
+        assert!(cache.promote(&"a"));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a", &"c", &"b"]);
+
+        assert!(cache.demote(&"c"));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a", &"b", &"c"]);
+
+        assert!(cache.demote(&"c"));
+        assert!(cache.demote(&"a"));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"b", &"c", &"a"]);
+        assert!(!cache.promote(&"d"));
+        assert!(!cache.demote(&"d"));
+
+        cache.put("d", 4, None);
+        assert!(!cache.contains_key(&"a"));
+    }
+
+    #[test]
+    fn test_borrowed_lookups() {
+        let mut cache: LruCache<String, i32> = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put("a".to_string(), 1, None);
+        cache.put("b".to_string(), 2, None);
+
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.peek("b"), Some(&2));
+        assert!(cache.contains_key("a"));
+        *cache.get_mut("a").unwrap() += 1;
+        assert_eq!(cache.peek_mut("a"), Some(&mut 2));
+        assert!(cache.touch("b"));
+        assert!(cache.demote("b"));
+        assert!(cache.promote("b"));
+        assert_eq!(cache.remove("a"), Some(2));
+    }
+
+    #[test]
+    fn test_non_clone_keys() {
+        #[derive(PartialEq, Eq, Hash)]
+        struct Key(String);
+
+        let mut cache = LruCache::new(2, CleanupMode::OnAccess);
+        cache.put(Key("a".to_string()), 1, None);
+        cache.put(Key("b".to_string()), 2, None);
+        cache.put(Key("a".to_string()), 3, None);
+        cache.put(Key("c".to_string()), 4, None);
+
+        assert_eq!(cache.get(&Key("a".to_string())), Some(&3));
+        assert_eq!(cache.get(&Key("b".to_string())), None);
+        assert_eq!(
+            cache.pop_lru().map(|(k, v)| (k.0, v)),
+            Some(("c".to_string(), 4))
+        );
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        use std::hash::BuildHasherDefault;
+
+        #[derive(Default)]
+        struct Fnv(u64);
+
+        impl Hasher for Fnv {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for byte in bytes {
+                    self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+
+        let mut cache = LruCache::with_hasher(
+            2,
+            CleanupMode::OnAccess,
+            BuildHasherDefault::<Fnv>::default(),
+        );
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"c"), Some(&3));
+        assert_eq!(cache.entry("d").or_insert(4), &mut 4);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"d", &"c"]);
+    }
+
+    #[test]
+    fn test_set_capacity() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
         cache.get(&"a");
+
+        cache.set_capacity(5);
         cache.put("d", 4, None);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.capacity(), 5);
+
+        cache.set_capacity(2);
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"d", &"a"]);
+
+        cache.put("e", 5, None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"e", &"d"]);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut cache = LruCache::new(1000, CleanupMode::OnDemand);
+        for i in 0..1000 {
+            cache.put(i, i, None);
+        }
+        cache.retain(|&k, _| k < 10);
+
+        cache.shrink_to_fit();
+        assert!(cache.map.capacity() < 1000);
+        assert_eq!(cache.len(), 10);
+        assert_eq!(cache.get(&5), Some(&5));
+
+        for i in 10..1000 {
+            cache.put(i, i, None);
+        }
+        assert_eq!(cache.len(), 1000);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut cache = LruCache::new(100, CleanupMode::OnDemand);
+        cache.shrink_to_fit();
+
+        cache.reserve(50);
+        assert!(cache.map.capacity() >= 50);
+
+        cache.reserve(usize::MAX);
+        assert!(cache.map.capacity() >= 100);
+
+        for i in 0..100 {
+            cache.put(i, i, None);
+        }
+        assert_eq!(cache.len(), 100);
+    }
+
+    #[test]
+    fn test_unbounded() {
+        let mut cache = LruCache::unbounded(CleanupMode::OnDemand);
+        for i in 0..10_000 {
+            cache.put(i, i, None);
+        }
+        assert_eq!(cache.len(), 10_000);
+        assert_eq!(cache.capacity(), usize::MAX);
+        assert_eq!(cache.pop_lru(), Some((0, 0)));
+
+        cache.put(-1, -1, Some(Duration::from_millis(50)));
+        thread::sleep(Duration::from_millis(100));
+        cache.evict_expired();
+        assert_eq!(cache.len(), 9_999);
+        assert_eq!(cache.keys().next(), Some(&9_999));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut cache = LruCache::new(0, CleanupMode::OnAccess);
+        assert!(cache.is_disabled());
+
+        assert_eq!(cache.put("a", 1, None), None);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.push("b", 2, None), Some(("b", 2)));
+        assert!(cache.is_empty());
+
+        assert_eq!(*cache.get_or_insert_with("c", || 3), 3);
+        assert_eq!(*cache.get_or_insert_with("c", || 4), 4);
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.is_empty());
+
+        let mut cache = LruCache::disabled();
+        cache.put("a", 1, None);
+        assert_eq!(cache.peek(&"a"), None);
+
+        cache.set_capacity(1);
+        cache.put("a", 1, None);
+        assert_eq!(cache.peek(&"a"), Some(&1));
+        cache.set_capacity(0);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_default_ttl() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.set_default_ttl(Some(Duration::from_millis(50)));
+
+        cache.put("a", 1, None);
+        cache.put("b", 2, Some(Duration::MAX));
+        cache.entry("c").or_insert(3);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.get(&"c"), None);
+    }
+
+    #[test]
+    fn test_ttl_jitter() {
+        let mut cache = LruCache::new(1000, CleanupMode::OnDemand);
+        cache.set_ttl_jitter(0.5);
+
+        let ttl = Duration::from_secs(100);
+        let start = Instant::now();
+        for i in 0..1000 {
+            cache.put(i, i, Some(ttl));
+        }
+
+        let elapsed = start.elapsed();
+        let expiries: Vec<_> = cache
+            .keys()
+            .map(|k| match cache.ttl(k) {
+                Some(Ttl::Remaining(remaining)) => remaining + elapsed,
+                other => panic!("unexpected ttl {other:?}"),
+            })
+            .collect();
+
+        assert!(
+            expiries
+                .iter()
+                .all(|e| *e >= ttl / 2 && *e <= ttl * 3 / 2 + Duration::from_secs(1))
+        );
+        assert!(expiries.iter().any(|e| *e < ttl * 9 / 10));
+        assert!(expiries.iter().any(|e| *e > ttl * 11 / 10));
+
+        cache.put(-1, -1, Some(Duration::MAX));
+        assert!(cache.contains_key(&-1));
+    }
+
+    #[test]
+    fn test_put_until() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        let now = SystemTime::now();
+
+        cache.put_until("a", 1, now + Duration::from_millis(50));
+        cache.put_until("b", 2, now + Duration::from_secs(60));
+        cache.put("c", 3, None);
+        assert_eq!(
+            cache.put_until("c", 4, now - Duration::from_secs(1)),
+            Some(3)
+        );
 
-        assert_eq!(cache.get(&"b"), None);
         assert_eq!(cache.get(&"a"), Some(&1));
-        assert_eq!(cache.get(&"c"), Some(&3));
-        assert_eq!(cache.get(&"d"), Some(&4));
+        assert_eq!(cache.get(&"c"), None);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
     }
 
     #[test]
-    fn test_no_memory_leaks() {
+    fn test_expire_after() {
+        let mut cache = LruCache::new(2, CleanupMode::OnDemand);
+        cache.put("a", 1, Some(Duration::from_millis(50)));
+        cache.put("b", 2, None);
+
+        assert!(cache.expire_after(&"a", Duration::from_secs(60)));
+        assert!(cache.expire_after(&"b", Duration::from_millis(50)));
+        assert!(!cache.expire_after(&"c", Duration::from_secs(1)));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"b", &"a"]);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), None);
+        assert!(!cache.expire_after(&"b", Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_persist() {
         let mut cache = LruCache::new(2, CleanupMode::OnAccess);
-        for i in 0..1000 {
-            cache.put(i, Box::new([0u8; 1024]), None);
+        cache.put("a", 1, Some(Duration::from_millis(50)));
+        cache.put("b", 2, Some(Duration::from_millis(50)));
+
+        assert!(cache.persist(&"a"));
+        assert!(!cache.persist(&"c"));
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), None);
+        assert!(!cache.persist(&"b"));
+    }
+
+    #[test]
+    fn test_ttl_query() {
+        let mut cache = LruCache::new(3, CleanupMode::OnDemand);
+        cache.put("a", 1, Some(Duration::from_secs(60)));
+        cache.put("b", 2, None);
+        cache.put("c", 3, Some(Duration::from_millis(10)));
+
+        match cache.ttl(&"a") {
+            Some(Ttl::Remaining(remaining)) => {
+                assert!(remaining <= Duration::from_secs(60));
+                assert!(remaining > Duration::from_secs(59));
+            }
+            other => panic!("unexpected ttl {other:?}"),
         }
+        assert_eq!(cache.ttl(&"b"), Some(Ttl::Persistent));
+        assert_eq!(cache.ttl(&"d"), None);
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(cache.ttl(&"c"), None);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .clock(clock.clone())
+            .build();
+
+        cache.put("a", 1, Some(Duration::from_secs(60)));
+        clock.advance(Duration::from_secs(10));
+        cache.get(&"a");
+        cache.get(&"a");
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(
+            cache.metadata(&"a"),
+            Some(EntryMeta {
+                age: Duration::from_secs(15),
+                idle: Duration::from_secs(5),
+                access_count: 2,
+                ttl: Ttl::Remaining(Duration::from_secs(45)),
+            })
+        );
+
+        cache.put("a", 2, None);
+        let meta = cache.metadata(&"a").unwrap();
+        assert_eq!((meta.age, meta.access_count), (Duration::ZERO, 2));
+        assert_eq!(cache.metadata(&"b"), None);
+    }
+
+    #[test]
+    fn test_get_stale() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .clock(clock.clone())
+            .stale_window(Duration::from_secs(30))
+            .build();
+        cache.put("a", 1, Some(Duration::from_secs(10)));
+        cache.put("b", 2, None);
+
+        assert_eq!(cache.get_stale(&"a"), Lookup::Fresh(&1));
+        assert_eq!(cache.get_stale(&"c"), Lookup::Miss);
+
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get_stale(&"a"), Lookup::Stale(&1));
         assert_eq!(cache.len(), 2);
+
+        clock.advance(Duration::from_secs(20));
+        cache.evict_expired();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get_stale(&"a"), Lookup::Miss);
+        assert_eq!(cache.get_stale(&"b"), Lookup::Fresh(&2));
+    }
+
+    #[test]
+    fn test_early_expiration() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder().clock(clock.clone()).build();
+        cache.set_early_expiration(Some(EarlyExpiration {
+            delta: Duration::from_secs(1),
+            beta: 1.0,
+        }));
+        cache.put("a", 1, Some(Duration::from_secs(60)));
+        cache.put("b", 2, None);
+
+        let misses = |cache: &mut LruCache<&str, i32>| {
+            (0..1000).filter(|_| cache.get(&"a").is_none()).count()
+        };
+        assert!(misses(&mut cache) < 10);
+
+        clock.advance(Duration::from_millis(59_500));
+        let near_expiry = misses(&mut cache);
+        assert!(near_expiry > 200 && near_expiry < 800, "{near_expiry}");
+        assert!(cache.contains_key(&"a"));
+        assert!((0..1000).all(|_| cache.get(&"b").is_some()));
+    }
+
+    #[test]
+    fn test_incremental_cleanup() {
+        let mut cache = LruCache::new(10, CleanupMode::Incremental(2));
+        for i in 0..5 {
+            cache.put(i, i, Some(Duration::from_millis(50)));
+        }
+        thread::sleep(Duration::from_millis(100));
+
+        cache.get(&100);
+        assert_eq!(cache.len(), 3);
+        cache.get(&100);
+        assert_eq!(cache.len(), 1);
+        cache.get(&100);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_expiry_index() {
+        let mut cache = LruCache::new(10, CleanupMode::OnDemand);
+        for i in 0..5 {
+            cache.put(i, i, Some(Duration::from_millis(50)));
+        }
+        cache.put(5, 5, None);
+        cache.put(0, 0, None);
+        cache.persist(&1);
+        cache.expire_after(&2, Duration::from_secs(60));
+        cache.remove(&3);
+        assert_eq!(cache.expiry.len(), 2);
+
+        thread::sleep(Duration::from_millis(100));
+        cache.evict_expired();
+
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&0, &5, &2, &1]);
+        assert_eq!(cache.expiry.len(), 1);
+
+        cache.clear();
+        assert_eq!(cache.expiry.len(), 0);
     }
 }
@@ -0,0 +1,175 @@
+use std::sync::mpsc::SyncSender;
+
+/// Причина удаления элемента из кеша
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RemovalCause {
+    /// Истекло время жизни
+    Expired,
+    /// Вытеснен из-за превышения ёмкости
+    SizeEvicted,
+    /// Значение заменено новым для того же ключа
+    Replaced,
+    /// Удалён явно: `retain`, `clear` или брошенный до конца `drain`
+    Explicit,
+}
+
+/// Слушатель удалений, см. [`LruCacheBuilder::removal_listener`](crate::LruCacheBuilder::removal_listener)
+///
+/// Получает ключ и значение во владение. Если значение возвращается вызывающему
+/// (`remove` и прежнее значение из `put`), вызывается [`RemovalListener::on_returned`];
+/// `pop_lru` и `drain` слушателю ничего не передают.
+pub trait RemovalListener<K, V> {
+    fn on_removal(&self, key: K, value: V, cause: RemovalCause);
+
+    /// Удаление, при котором значение получает вызывающий: [`RemovalCause::Explicit`]
+    /// для `remove` и [`RemovalCause::Replaced`] для перезаписи через `put`
+    ///
+    /// По умолчанию ничего не делает, поэтому слушатель-замыкание такие удаления
+    /// не видит; для учёта ссылок или дескрипторов реализуйте трейт для своего типа.
+    fn on_returned(&self, key: &K, value: &V, cause: RemovalCause) {
+        let _ = (key, value, cause);
+    }
+}
+
+impl<K, V, F> RemovalListener<K, V> for F
+where
+    F: Fn(K, V, RemovalCause),
+{
+    fn on_removal(&self, key: K, value: V, cause: RemovalCause) {
+        self(key, value, cause)
+    }
+}
+
+/// Передача событий в ограниченный канал для фонового обработчика
+///
+/// Операции кеша не блокируются: при заполненном или закрытом канале событие отбрасывается.
+impl<K, V> RemovalListener<K, V> for SyncSender<(K, V, RemovalCause)> {
+    fn on_removal(&self, key: K, value: V, cause: RemovalCause) {
+        let _ = self.try_send((key, value, cause));
+    }
+}
+
+/// Передача событий в ограниченный канал tokio; как и для `SyncSender`,
+/// при заполненном канале событие отбрасывается
+#[cfg(feature = "tokio")]
+impl<K, V> RemovalListener<K, V> for tokio::sync::mpsc::Sender<(K, V, RemovalCause)> {
+    fn on_removal(&self, key: K, value: V, cause: RemovalCause) {
+        let _ = self.try_send((key, value, cause));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, LruCache, MockClock};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_removal_listener() {
+        let clock = MockClock::new();
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let log = removed.clone();
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .removal_listener(move |key: &'static str, value: i32, cause| {
+                log.lock().unwrap().push((key, value, cause));
+            })
+            .build();
+
+        cache.put("a", 1, Some(Duration::from_secs(10)));
+        cache.put("b", 2, None);
+        cache.push("b", 3, None);
+        cache.put("c", 4, None);
+        assert_eq!(cache.put("c", 5, None), Some(4));
+        assert_eq!(cache.remove(&"c"), Some(5));
+
+        cache.put("d", 6, Some(Duration::from_secs(10)));
+        clock.advance(Duration::from_secs(10));
+        cache.evict_expired();
+        cache.put("e", 7, None);
+        cache.retain(|key, _| *key != "e");
+        cache.put("f", 8, None);
+        cache.clear();
+
+        assert_eq!(
+            *removed.lock().unwrap(),
+            vec![
+                ("b", 2, RemovalCause::Replaced),
+                ("a", 1, RemovalCause::SizeEvicted),
+                ("d", 6, RemovalCause::Expired),
+                ("e", 7, RemovalCause::Explicit),
+                ("f", 8, RemovalCause::Explicit),
+                ("b", 3, RemovalCause::Explicit),
+            ]
+        );
+    }
+
+    /// Счётчик живых значений, как для дескрипторов или ссылок
+    struct Handles(Mutex<Vec<(RemovalCause, bool)>>);
+
+    impl RemovalListener<u32, u32> for Arc<Handles> {
+        fn on_removal(&self, _: u32, _: u32, cause: RemovalCause) {
+            self.0.lock().unwrap().push((cause, false));
+        }
+
+        fn on_returned(&self, _: &u32, _: &u32, cause: RemovalCause) {
+            self.0.lock().unwrap().push((cause, true));
+        }
+    }
+
+    #[test]
+    fn test_removal_returned() {
+        let handles = Arc::new(Handles(Mutex::new(Vec::new())));
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .removal_listener(handles.clone())
+            .build();
+
+        cache.put(1, 10, None);
+        assert_eq!(cache.put(1, 11, None), Some(10));
+        cache.push(1, 12, None);
+        assert_eq!(cache.remove(&1), Some(12));
+        assert_eq!(cache.remove(&1), None);
+
+        assert_eq!(
+            *handles.0.lock().unwrap(),
+            vec![
+                (RemovalCause::Replaced, true),
+                (RemovalCause::Replaced, false),
+                (RemovalCause::Explicit, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removal_channel() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let mut cache = LruCache::builder().capacity(1).removal_listener(tx).build();
+
+        cache.put(1, "a", None);
+        cache.put(2, "b", None);
+        cache.put(3, "c", None);
+        assert_eq!(rx.try_recv(), Ok((1, "a", RemovalCause::SizeEvicted)));
+        assert!(rx.try_recv().is_err());
+
+        drop(cache);
+        assert_eq!(rx.recv(), Err(mpsc::RecvError));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_removal_channel_tokio() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let mut cache = LruCache::builder().capacity(1).removal_listener(tx).build();
+
+        cache.put(1, "a", None);
+        cache.put(2, "b", None);
+        cache.clear();
+        assert_eq!(rx.try_recv(), Ok((1, "a", RemovalCause::SizeEvicted)));
+        assert_eq!(rx.try_recv(), Ok((2, "b", RemovalCause::Explicit)));
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(feature = "tokio")]
+use std::future::Future;
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+use crate::AsyncLruCache;
+use crate::{DefaultHashBuilder, Loader, SyncLruCache};
+
+/// Потокобезопасный кеш, который сам загружает отсутствующие элементы
+///
+/// Загрузчик задаётся при создании, и `get` вызывает его при промахе через
+/// [`SyncLruCache::try_get_with`], поэтому одновременные промахи по ключу загружают его
+/// один раз. Остальные методы доступны через [`LoadingCache::cache`].
+pub struct LoadingCache<K, V, S = DefaultHashBuilder> {
+    cache: SyncLruCache<K, V, S>,
+    loader: Arc<dyn Loader<K, V> + Send + Sync>,
+}
+
+impl<K, V, S> Clone for LoadingCache<K, V, S> {
+    fn clone(&self) -> Self {
+        LoadingCache {
+            cache: self.cache.clone(),
+            loader: self.loader.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher> LoadingCache<K, V, S> {
+    pub fn new<L>(cache: impl Into<SyncLruCache<K, V, S>>, loader: L) -> Self
+    where
+        L: Loader<K, V> + Send + Sync + 'static,
+    {
+        LoadingCache {
+            cache: cache.into(),
+            loader: Arc::new(loader),
+        }
+    }
+
+    /// Значение из кеша или от загрузчика; `None`, если загрузчик не вернул значение
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.cache
+            .try_get_with(key.clone(), || self.loader.load(key).ok_or(()))
+            .ok()
+    }
+
+    pub fn cache(&self) -> &SyncLruCache<K, V, S> {
+        &self.cache
+    }
+}
+
+#[cfg(feature = "tokio")]
+type BoxLoad<V> = Pin<Box<dyn Future<Output = Option<V>> + Send>>;
+
+/// Асинхронный вариант [`LoadingCache`] поверх [`AsyncLruCache`]
+#[cfg(feature = "tokio")]
+pub struct AsyncLoadingCache<K, V, S = DefaultHashBuilder> {
+    cache: AsyncLruCache<K, V, S>,
+    loader: Arc<dyn Fn(K) -> BoxLoad<V> + Send + Sync>,
+}
+
+#[cfg(feature = "tokio")]
+impl<K, V, S> Clone for AsyncLoadingCache<K, V, S> {
+    fn clone(&self) -> Self {
+        AsyncLoadingCache {
+            cache: self.cache.clone(),
+            loader: self.loader.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher> AsyncLoadingCache<K, V, S> {
+    /// Кеш с загрузчиком `loader(key)`, возвращающим `None`, если значение получить не удалось
+    pub fn new<F, Fut>(cache: impl Into<AsyncLruCache<K, V, S>>, loader: F) -> Self
+    where
+        F: Fn(K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<V>> + Send + 'static,
+    {
+        AsyncLoadingCache {
+            cache: cache.into(),
+            loader: Arc::new(move |key| Box::pin(loader(key))),
+        }
+    }
+
+    /// Значение из кеша или от загрузчика, см. [`AsyncLruCache::try_get_with`]
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.cache
+            .try_get_with(key.clone(), || async {
+                (self.loader)(key.clone()).await.ok_or(())
+            })
+            .await
+            .ok()
+    }
+
+    pub fn cache(&self) -> &AsyncLruCache<K, V, S> {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, LruCache};
+
+    #[test]
+    fn test_loading_cache() {
+        let cache = LoadingCache::new(LruCache::new(10, CleanupMode::OnAccess), |key: &u32| {
+            (*key < 100).then(|| key * 2)
+        });
+
+        assert_eq!(cache.get(&21), Some(42));
+        assert_eq!(cache.get(&500), None);
+        assert_eq!(cache.cache().len(), 1);
+        assert_eq!(cache.cache().stats().misses, 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_loading_cache() {
+        let cache = AsyncLoadingCache::new(
+            LruCache::new(10, CleanupMode::OnAccess),
+            |key: u32| async move { Some(key.to_string()) },
+        );
+
+        assert_eq!(cache.get(&7).await, Some("7".to_string()));
+        assert!(cache.cache().contains_key(&7).await);
+    }
+}
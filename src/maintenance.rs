@@ -0,0 +1,76 @@
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::LruCache;
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Eq + Hash + Send + 'static,
+    V: Send + 'static,
+    S: BuildHasher + Send + 'static,
+{
+    /// Запускает задачу tokio, удаляющую просроченные элементы и выполняющую
+    /// отложенные обновления раз в `interval`
+    ///
+    /// Задача держит только слабую ссылку и завершается после удаления последнего
+    /// дескриптора кеша; досрочно её можно отменить через [`JoinHandle::abort`].
+    pub fn spawn_maintenance(cache: &Arc<Mutex<Self>>, interval: Duration) -> JoinHandle<()> {
+        let cache = Arc::downgrade(cache);
+
+        tokio::spawn(async move {
+            let mut ticks = time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // первый тик срабатывает сразу
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.lock().await.run_maintenance();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spawn_maintenance() {
+        let clock = MockClock::new();
+        let cache = Arc::new(Mutex::new(
+            LruCache::builder()
+                .capacity(10)
+                .cleanup_mode(CleanupMode::OnDemand)
+                .clock(clock.clone())
+                .build(),
+        ));
+        {
+            let mut cache = cache.lock().await;
+            cache.put("a", 1, Some(Duration::from_millis(20)));
+            cache.put("b", 2, None);
+        }
+
+        let task = LruCache::spawn_maintenance(&cache, Duration::from_millis(10));
+        clock.advance(Duration::from_millis(30));
+        // время tokio остановлено и сдвигается только на ожидании, поэтому тики детерминированы
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cache.lock().await.len(), 1);
+        assert!(!task.is_finished());
+
+        drop(cache);
+        time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("задача не завершилась после удаления кеша")
+            .unwrap();
+    }
+}
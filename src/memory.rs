@@ -0,0 +1,53 @@
+use std::hash::Hash;
+use std::mem::size_of;
+use std::ptr::NonNull;
+
+use deepsize::DeepSizeOf;
+
+use crate::{CleanupMode, KeyRef, LruCache, Node, Weigher};
+
+impl<K: Eq + Hash + DeepSizeOf, V: DeepSizeOf> LruCache<K, V> {
+    /// Кеш, ограниченный оценкой занимаемой памяти в байтах
+    ///
+    /// Вес элемента включает узел списка, запись карты и данные ключа и значения в куче.
+    /// Оценка выполняется при вставке, поэтому рост значения через `get_mut` не учитывается.
+    pub fn with_memory_limit(max_bytes: usize, cleanup_mode: CleanupMode) -> Self {
+        Self::with_weigher(max_bytes, cleanup_mode, MemoryWeigher)
+    }
+}
+
+/// Вес элемента в байтах по оценке `DeepSizeOf`
+struct MemoryWeigher;
+
+impl<K: DeepSizeOf, V: DeepSizeOf> Weigher<K, V> for MemoryWeigher {
+    fn weight(&self, key: &K, value: &V) -> u32 {
+        // ключ и значение хранятся внутри узла, поэтому учитываем только их данные в куче
+        let overhead = size_of::<Node<K, V>>() + size_of::<(KeyRef<K>, NonNull<Node<K, V>>)>();
+        let heap = key.deep_size_of() - size_of::<K>() + value.deep_size_of() - size_of::<V>();
+
+        u32::try_from(overhead + heap).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_limit() {
+        let per_entry = MemoryWeigher.weight(&0u32, &vec![0u8; 1000]);
+        assert!(per_entry >= 1000);
+
+        let mut cache = LruCache::with_memory_limit(per_entry as usize * 3, CleanupMode::OnDemand);
+        for i in 0..10u32 {
+            cache.put(i, vec![0u8; 1000], None);
+        }
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.total_weight(), per_entry as u64 * 3);
+
+        cache.put(100, vec![0u8; 10], None);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.total_weight() < per_entry as u64 * 3);
+    }
+}
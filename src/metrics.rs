@@ -0,0 +1,82 @@
+use std::hash::{BuildHasher, Hash};
+
+use crate::LruCache;
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Передаёт показатели кеша в [metrics](https://crates.io/crates/metrics) с меткой
+    /// `cache`, заданной [`LruCacheBuilder::metrics_name`](crate::LruCacheBuilder::metrics_name)
+    ///
+    /// Счётчики `lru_cache_{hits,misses,insertions,evictions,expirations}_total` и показатели
+    /// `lru_cache_entries`, `lru_cache_weight` (оценка байт при `with_memory_limit`).
+    /// Без имени ничего не делает; фоновая очистка вызывает метод на каждом шаге.
+    pub fn publish_metrics(&self) {
+        let Some(name) = self.metrics_name.as_ref() else {
+            return;
+        };
+        let total = &self.stats.total;
+
+        let counters = [
+            ("lru_cache_hits_total", total.hits),
+            ("lru_cache_misses_total", total.misses),
+            ("lru_cache_insertions_total", total.insertions),
+            ("lru_cache_evictions_total", total.evictions),
+            ("lru_cache_expirations_total", total.expirations),
+        ];
+        for (metric, value) in counters {
+            ::metrics::counter!(metric, "cache" => name.clone()).absolute(value);
+        }
+
+        ::metrics::gauge!("lru_cache_entries", "cache" => name.clone()).set(self.len() as f64);
+        ::metrics::gauge!("lru_cache_weight", "cache" => name.clone()).set(self.weight as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_publish_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let mut cache = LruCache::builder()
+            .capacity(1)
+            .metrics_name("sessions")
+            .build();
+
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.get(&"b");
+        cache.stats_snapshot();
+        metrics::with_local_recorder(&recorder, || cache.publish_metrics());
+
+        let values: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<_> = key.key().labels().map(|l| l.value().to_owned()).collect();
+                assert_eq!(labels, ["sessions"]);
+                (key.key().name().to_owned(), value)
+            })
+            .collect();
+        let value = |name: &str| {
+            values
+                .iter()
+                .find(|(metric, _)| metric == name)
+                .map(|(_, value)| value)
+        };
+
+        // сброс через `stats_snapshot` не влияет на экспорт
+        assert_eq!(value("lru_cache_hits_total"), Some(&DebugValue::Counter(1)));
+        assert_eq!(
+            value("lru_cache_evictions_total"),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            value("lru_cache_entries"),
+            Some(&DebugValue::Gauge(1.0.into()))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::time::{Duration, Instant};
+
+use crate::{LruCache, RemovalCause};
+
+/// Результат [`LruCache::get_cached`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cached<T> {
+    Hit(T),
+    /// Ключ отмечен как отсутствующий в источнике, см. [`LruCache::put_negative`]
+    NegativeHit,
+    Miss,
+}
+
+/// Отметки отсутствующих ключей с моментом истечения
+///
+/// Ключ не может одновременно иметь значение и отметку: `put_negative` удаляет
+/// значение, а добавление значения снимает отметку.
+#[derive(Clone)]
+pub(crate) struct NegativeEntries<K> {
+    // момент истечения и порядковый номер, различающий отметки с одним моментом
+    map: HashMap<K, (Instant, u64)>,
+    // отметки в порядке истечения: при заполнении вытесняется первая
+    order: BTreeMap<(Instant, u64), K>,
+    next_seq: u64,
+    pub(crate) capacity: usize,
+    // ёмкость задана явно и не меняется вместе с ёмкостью кеша
+    pub(crate) fixed: bool,
+}
+
+impl<K> NegativeEntries<K> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        NegativeEntries {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            next_seq: 0,
+            capacity,
+            fixed: false,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+
+impl<K: Eq + Hash> NegativeEntries<K> {
+    pub(crate) fn remove<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.map.is_empty()
+            && let Some(position) = self.map.remove(key)
+        {
+            self.order.remove(&position);
+        }
+    }
+
+    /// Изменяет ёмкость, при уменьшении вытесняя ближайшие к истечению отметки
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.map.len() > capacity
+            && let Some((_, soonest)) = self.order.pop_first()
+        {
+            self.map.remove(&soonest);
+        }
+    }
+
+    fn insert(&mut self, key: K, expires_at: Instant, now: Instant)
+    where
+        K: Clone,
+    {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(&key);
+        while let Some(entry) = self.order.first_entry()
+            && entry.key().0 <= now
+        {
+            self.map.remove(&entry.remove());
+        }
+        if self.map.len() >= self.capacity
+            && let Some((_, soonest)) = self.order.pop_first()
+        {
+            self.map.remove(&soonest);
+        }
+
+        let position = (expires_at, self.next_seq);
+        self.next_seq += 1;
+        self.order.insert(position, key.clone());
+        self.map.insert(key, position);
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Отмечает ключ как отсутствующий в источнике на время `ttl`
+    ///
+    /// Пока отметка действует, [`LruCache::get_cached`] возвращает [`Cached::NegativeHit`],
+    /// и запрос к источнику можно не повторять. Значение ключа, если оно было,
+    /// удаляется с причиной [`RemovalCause::Replaced`]; `put` снимает отметку.
+    /// Число отметок ограничено отдельно, см. [`LruCacheBuilder::negative_capacity`](crate::LruCacheBuilder::negative_capacity);
+    /// при заполнении вытесняется ближайшая к истечению.
+    pub fn put_negative(&mut self, key: K, ttl: Duration)
+    where
+        K: Clone,
+    {
+        let now = self.now();
+        if let Some(&node_ptr) = self.map.get(crate::KeyWrapper::from_ref(&key)) {
+            let node = self.unlink(node_ptr);
+            let cause = if node.expired_at(now) {
+                RemovalCause::Expired
+            } else {
+                RemovalCause::Replaced
+            };
+            self.notify(node.key, node.value, cause);
+        }
+
+        if let Some(expires_at) = now.checked_add(ttl) {
+            self.negative.insert(key, expires_at, now);
+        }
+    }
+
+    /// Чтение с учётом отметок отсутствия: отметка считается попаданием в статистике
+    pub fn get_cached<Q>(&mut self, key: &Q) -> Cached<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.now();
+        match self.negative.map.get_key_value(key) {
+            Some((key, &(expires_at, _))) if expires_at > now => {
+                self.stats.record_hit(now);
+                if let Some(hooks) = self.hooks.as_ref() {
+                    hooks.on_hit(key);
+                }
+                return Cached::NegativeHit;
+            }
+            Some(_) => self.negative.remove(key),
+            None => {}
+        }
+
+        match self.get(key) {
+            Some(value) => Cached::Hit(value),
+            None => Cached::Miss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+
+    #[test]
+    fn test_negative_cache() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .negative_capacity(2)
+            .cleanup_mode(CleanupMode::OnAccess)
+            .clock(clock.clone())
+            .build();
+
+        cache.put("a", 1, None);
+        cache.put_negative("a", Duration::from_secs(5));
+        cache.put_negative("b", Duration::from_secs(10));
+        assert_eq!(cache.get_cached(&"a"), Cached::NegativeHit);
+        assert_eq!(cache.get_cached(&"b"), Cached::NegativeHit);
+        assert_eq!(cache.get_cached(&"c"), Cached::Miss);
+        assert!(cache.is_empty());
+
+        // новая отметка вытесняет ближайшую к истечению
+        cache.put_negative("c", Duration::from_secs(20));
+        assert_eq!(cache.get_cached(&"a"), Cached::Miss);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.get_cached(&"b"), Cached::Miss);
+        cache.put("c", 3, None);
+        assert_eq!(cache.get_cached(&"c"), Cached::Hit(&3));
+
+        // повторная отметка не оставляет прежней позиции в порядке истечения
+        cache.put_negative("d", Duration::from_secs(5));
+        cache.put_negative("d", Duration::from_secs(15));
+        assert_eq!(cache.negative.map.len(), 1);
+        assert_eq!(cache.negative.order.len(), 1);
+    }
+
+    #[test]
+    fn test_negative_capacity_follows_cache() {
+        let mut cache = LruCache::<&str, i32>::new(3, CleanupMode::OnAccess);
+        cache.put_negative("a", Duration::from_secs(30));
+        cache.put_negative("b", Duration::from_secs(10));
+        cache.put_negative("c", Duration::from_secs(20));
+
+        cache.set_capacity(1);
+        assert_eq!(cache.negative.capacity, 1);
+        assert_eq!(cache.get_cached(&"a"), Cached::NegativeHit);
+        assert_eq!(cache.negative.order.len(), 1);
+
+        // явно заданная ёмкость отметок от ёмкости кеша не зависит
+        let mut cache = LruCache::<&str, i32>::builder()
+            .capacity(3)
+            .negative_capacity(2)
+            .build();
+        cache.set_capacity(1);
+        assert_eq!(cache.negative.capacity, 2);
+    }
+}
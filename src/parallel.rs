@@ -0,0 +1,60 @@
+use std::hash::{BuildHasher, Hash};
+
+use rayon::prelude::*;
+
+use crate::{LruCache, ShardedLruCache};
+
+impl<K: Eq + Hash + Sync, V: Sync, S: BuildHasher> LruCache<K, V, S> {
+    /// Параллельный итератор rayon по непросроченным элементам
+    ///
+    /// Ссылки сначала собираются в вектор в порядке LRU (один проход по списку),
+    /// а обработка элементов распределяется по потокам пула.
+    pub fn par_iter(&self) -> rayon::vec::IntoIter<(&K, &V)> {
+        self.iter().collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+impl<K, V, S> ShardedLruCache<K, V, S>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    S: BuildHasher + Send + Sync,
+{
+    /// Параллельный итератор по копиям элементов: сегменты обходятся одновременно,
+    /// и каждый блокируется только на время копирования своих элементов
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (K, V)> + '_ {
+        (0..self.shard_count())
+            .into_par_iter()
+            .flat_map_iter(|index| {
+                let shard = self.lock_index(index);
+                shard
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_par_iter() {
+        let mut cache = LruCache::new(1000, CleanupMode::OnAccess);
+        for i in 0..1000u64 {
+            cache.put(i, i * 2, None);
+        }
+        assert_eq!(
+            cache.par_iter().map(|(_, value)| value).sum::<u64>(),
+            999_000
+        );
+
+        let sharded = ShardedLruCache::with_shards(1000, 8, CleanupMode::OnAccess);
+        for i in 0..100u64 {
+            sharded.put(i, i, None);
+        }
+        assert_eq!(sharded.par_iter().count(), 100);
+    }
+}
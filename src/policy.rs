@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+
+/// Политика времени жизни элементов (как `Expiry` в Caffeine), см. [`LruCacheBuilder::expiry`](crate::LruCacheBuilder::expiry)
+///
+/// Позволяет вычислять TTL по ключу и значению и менять его при чтении и перезаписи.
+/// Возвращаемое `None` означает отсутствие ограничения. Политика применяется к
+/// вставкам без явного `ttl`; явно переданный `ttl` имеет приоритет.
+pub trait Expiry<K, V> {
+    /// Время жизни нового элемента
+    fn expire_after_create(&self, key: &K, value: &V, now: Instant) -> Option<Duration>;
+
+    /// Новое время жизни после чтения через `get`/`get_mut`; `remaining` - текущий остаток
+    fn expire_after_read(
+        &self,
+        key: &K,
+        value: &V,
+        now: Instant,
+        remaining: Option<Duration>,
+    ) -> Option<Duration> {
+        let _ = (key, value, now);
+        remaining
+    }
+
+    /// Новое время жизни после перезаписи значения; `remaining` - остаток прежнего значения
+    fn expire_after_update(
+        &self,
+        key: &K,
+        value: &V,
+        now: Instant,
+        remaining: Option<Duration>,
+    ) -> Option<Duration> {
+        let _ = (key, value, now);
+        remaining
+    }
+}
+
+/// Значение с собственным сроком действия (JWT, запись DNS, подписанная ссылка)
+pub trait Expirable {
+    /// Момент истечения; `None` - без ограничения
+    fn expires_at(&self) -> Option<Instant>;
+}
+
+/// Политика, берущая срок из самого значения, см. [`Expirable`]
+///
+/// Подключается через `LruCacheBuilder::expiry(ValueExpiry)`: тогда `put(k, v, None)`
+/// не требует TTL, срок пересчитывается при каждой перезаписи.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueExpiry;
+
+impl<K, V: Expirable> Expiry<K, V> for ValueExpiry {
+    fn expire_after_create(&self, _: &K, value: &V, now: Instant) -> Option<Duration> {
+        value
+            .expires_at()
+            .map(|expires_at| expires_at.saturating_duration_since(now))
+    }
+
+    fn expire_after_update(
+        &self,
+        key: &K,
+        value: &V,
+        now: Instant,
+        _: Option<Duration>,
+    ) -> Option<Duration> {
+        self.expire_after_create(key, value, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, Clock, LruCache, MockClock, Ttl};
+
+    /// TTL по длине значения, скользящее продление до 10 секунд при чтении
+    struct SessionExpiry;
+
+    impl Expiry<&'static str, String> for SessionExpiry {
+        fn expire_after_create(&self, _: &&str, value: &String, _: Instant) -> Option<Duration> {
+            Some(Duration::from_secs(value.len() as u64))
+        }
+
+        fn expire_after_read(
+            &self,
+            _: &&str,
+            _: &String,
+            _: Instant,
+            remaining: Option<Duration>,
+        ) -> Option<Duration> {
+            remaining.map(|remaining| remaining.max(Duration::from_secs(10)))
+        }
+    }
+
+    struct Token {
+        exp: Option<Instant>,
+    }
+
+    impl Expirable for Token {
+        fn expires_at(&self) -> Option<Instant> {
+            self.exp
+        }
+    }
+
+    #[test]
+    fn test_value_expiry() {
+        let clock = MockClock::new();
+        let now = clock.now();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .expiry(ValueExpiry)
+            .build();
+
+        let exp = Some(now + Duration::from_secs(30));
+        cache.put("a", Token { exp }, None);
+        cache.put("b", Token { exp: None }, None);
+        cache.put("c", Token { exp: Some(now) }, None);
+        assert_eq!(
+            cache.ttl(&"a"),
+            Some(Ttl::Remaining(Duration::from_secs(30)))
+        );
+        assert_eq!(cache.ttl(&"b"), Some(Ttl::Persistent));
+        assert!(!cache.contains_key(&"c"));
+
+        let exp = Some(now + Duration::from_secs(60));
+        cache.put("a", Token { exp }, None);
+        clock.advance(Duration::from_secs(45));
+        assert!(cache.get(&"a").is_some());
+    }
+
+    #[test]
+    fn test_expiry_policy() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .expiry(SessionExpiry)
+            .build();
+
+        cache.put("a", "12345".to_string(), None);
+        cache.put("b", "12".to_string(), Some(Duration::from_secs(60)));
+        assert_eq!(
+            cache.ttl(&"a"),
+            Some(Ttl::Remaining(Duration::from_secs(5)))
+        );
+        assert_eq!(
+            cache.ttl(&"b"),
+            Some(Ttl::Remaining(Duration::from_secs(60)))
+        );
+
+        clock.advance(Duration::from_secs(4));
+        cache.get(&"a");
+        assert_eq!(
+            cache.ttl(&"a"),
+            Some(Ttl::Remaining(Duration::from_secs(10)))
+        );
+
+        // перезапись сохраняет остаток
+        clock.advance(Duration::from_secs(3));
+        cache.put("a", "x".to_string(), None);
+        assert_eq!(
+            cache.ttl(&"a"),
+            Some(Ttl::Remaining(Duration::from_secs(7)))
+        );
+
+        clock.advance(Duration::from_secs(7));
+        assert_eq!(cache.get(&"a"), None);
+    }
+
+    /// Элемент живёт до первого чтения
+    struct ReadOnce;
+
+    impl Expiry<i32, i32> for ReadOnce {
+        fn expire_after_create(&self, _: &i32, _: &i32, _: Instant) -> Option<Duration> {
+            None
+        }
+
+        fn expire_after_read(
+            &self,
+            _: &i32,
+            _: &i32,
+            _: Instant,
+            _: Option<Duration>,
+        ) -> Option<Duration> {
+            Some(Duration::ZERO)
+        }
+    }
+
+    #[test]
+    fn test_expire_after_read_batch() {
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(MockClock::new())
+            .expiry(ReadOnce)
+            .build();
+        cache.put(1, 10, None);
+        cache.put(2, 20, None);
+
+        // повторный ключ в пакете не удаляет уже найденный элемент
+        assert_eq!(cache.get_batch([&1, &1]), vec![(&1, &10), (&1, &10)]);
+        assert_eq!(cache.len(), 2);
+        let [first, second] = cache.get_many_mut([&2, &1]);
+        assert_eq!((first, second), (Some(&mut 20), None));
+        assert_eq!(cache.get_batch([&1, &2]), vec![]);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use std::hash::{BuildHasher, Hash};
+use std::ptr::NonNull;
+use std::time::{Duration, Instant};
+
+use crate::{LruCache, Node, RemovalCause};
+
+/// Загрузчик значения по ключу; `None` означает, что значение получить не удалось
+pub trait Loader<K, V> {
+    fn load(&self, key: &K) -> Option<V>;
+}
+
+impl<K, V, F> Loader<K, V> for F
+where
+    F: Fn(&K) -> Option<V>,
+{
+    fn load(&self, key: &K) -> Option<V> {
+        self(key)
+    }
+}
+
+/// Когда выполняется упреждающее обновление, см. [`LruCacheBuilder::refresh_ahead`](crate::LruCacheBuilder::refresh_ahead)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshMode {
+    /// Синхронно при чтении элемента через `get`/`get_mut`
+    OnAccess,
+    /// Ставится в очередь при чтении и выполняется в [`LruCache::run_pending_refreshes`]
+    /// (его же вызывают фоновые потоки и задачи очистки)
+    Deferred,
+}
+
+pub(crate) struct RefreshAhead<K, V> {
+    pub(crate) threshold: Duration,
+    pub(crate) mode: RefreshMode,
+    pub(crate) loader: Box<dyn Loader<K, V> + Send + Sync>,
+    // копия ключа для слушателя: заменённое значение уходит ему вместе с ключом
+    pub(crate) clone_key: fn(&K) -> K,
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Обновление прочитанного элемента, оставшееся время жизни которого меньше порога
+    pub(crate) fn refresh_ahead(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        match self.refresh.as_ref().map(|refresh| refresh.mode) {
+            Some(RefreshMode::OnAccess) if self.refresh_due(node_ptr, now) => {
+                self.reload(node_ptr, now)
+            }
+            Some(RefreshMode::Deferred) => self.queue_refresh(node_ptr, now),
+            _ => {}
+        }
+    }
+
+    /// Ставит элемент в очередь [`LruCache::run_pending_refreshes`] независимо от режима
+    pub(crate) fn queue_refresh(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        if self.refresh_due(node_ptr, now) {
+            unsafe { (*node_ptr.as_ptr()).refresh_queued = true };
+            self.pending_refresh.push(node_ptr);
+        }
+    }
+
+    fn refresh_due(&self, node_ptr: NonNull<Node<K, V>>, now: Instant) -> bool {
+        let Some(refresh) = self.refresh.as_ref() else {
+            return false;
+        };
+
+        let node = unsafe { &*node_ptr.as_ptr() };
+        let due = node.expires_at.is_some_and(|expires_at| {
+            expires_at.saturating_duration_since(now) < refresh.threshold
+        });
+        due && !node.refresh_queued
+    }
+
+    /// Загружает новое значение узла; время жизни назначается как для `put(.., None)`,
+    /// прежнее значение получает слушатель с причиной [`RemovalCause::Replaced`]
+    fn reload(&mut self, node_ptr: NonNull<Node<K, V>>, now: Instant) {
+        let Some(refresh) = self.refresh.as_ref() else {
+            return;
+        };
+        let clone_key = refresh.clone_key;
+        let key = unsafe { &(*node_ptr.as_ptr()).key };
+        let Some(value) = refresh.loader.load(key) else {
+            return;
+        };
+
+        let expires_at = self.expires_at_for(key, &value, None, now);
+        let weight = self.weigh(key, &value);
+        let old = unsafe {
+            let node = &mut *node_ptr.as_ptr();
+            self.weight = self.weight - node.weight as u64 + weight as u64;
+            node.weight = weight;
+            node.inserted_at = now;
+            std::mem::replace(&mut node.value, value)
+        };
+        self.set_expiry(node_ptr, expires_at);
+        if self.listener.is_some() {
+            let key = clone_key(unsafe { &(*node_ptr.as_ptr()).key });
+            self.notify(key, old, RemovalCause::Replaced);
+        }
+        self.evict_overflow(node_ptr, now);
+    }
+
+    /// Выполняет отложенные обновления режима [`RefreshMode::Deferred`], возвращает их число
+    pub fn run_pending_refreshes(&mut self) -> usize {
+        let now = self.now();
+        let mut count = 0;
+
+        // узлы извлекаются по одному: обновление может вытеснить другие узлы очереди,
+        // и `unlink` убирает их из неё
+        while let Some(node_ptr) = self.pending_refresh.pop() {
+            unsafe { (*node_ptr.as_ptr()).refresh_queued = false };
+            self.reload(node_ptr, now);
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock, Ttl};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_refresh_ahead() {
+        static LOADS: AtomicU32 = AtomicU32::new(0);
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .default_ttl(Duration::from_secs(60))
+            .refresh_ahead(
+                Duration::from_secs(10),
+                RefreshMode::OnAccess,
+                |key: &&str| {
+                    LOADS.fetch_add(1, Ordering::Relaxed);
+                    (*key != "missing").then_some(key.len())
+                },
+            )
+            .build();
+
+        cache.put("abc", 0, None);
+        cache.put("missing", 0, None);
+        assert_eq!(cache.get(&"abc"), Some(&0));
+
+        clock.advance(Duration::from_secs(55));
+        assert_eq!(cache.get(&"abc"), Some(&3));
+        assert_eq!(
+            cache.ttl(&"abc"),
+            Some(Ttl::Remaining(Duration::from_secs(60)))
+        );
+        assert_eq!(cache.get(&"missing"), Some(&0));
+        assert_eq!(LOADS.load(Ordering::Relaxed), 2);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.get(&"missing"), None);
+        assert_eq!(cache.get(&"abc"), Some(&3));
+    }
+
+    #[test]
+    fn test_refresh_ahead_deferred() {
+        let clock = MockClock::new();
+        let (tx, rx) = mpsc::sync_channel(8);
+        let mut cache = LruCache::builder()
+            .capacity(10)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .default_ttl(Duration::from_secs(60))
+            .refresh_ahead(
+                Duration::from_secs(10),
+                RefreshMode::Deferred,
+                |key: &u32| Some(key * 10),
+            )
+            .removal_listener(tx)
+            .build();
+
+        for i in 0..3 {
+            cache.put(i, 0, None);
+        }
+        clock.advance(Duration::from_secs(55));
+        assert_eq!(cache.get(&1), Some(&0));
+        assert_eq!(cache.get(&1), Some(&0));
+        assert_eq!(cache.get(&2), Some(&0));
+        cache.remove(&2);
+
+        assert_eq!(cache.run_pending_refreshes(), 1);
+        assert_eq!(cache.peek(&1), Some(&10));
+        assert_eq!(cache.peek(&0), Some(&0));
+        assert_eq!(cache.run_pending_refreshes(), 0);
+        assert_eq!(rx.try_recv(), Ok((1, 0, RemovalCause::Replaced)));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{CleanupMode, LruCache};
+
+/// Представление кеша: ёмкость и элементы от недавно использованных к давно
+/// использованным с оставшимся на момент сериализации TTL (`None` - без ограничения)
+#[derive(Serialize)]
+pub(crate) struct SnapshotRef<'a, K, V> {
+    capacity: usize,
+    entries: Vec<(&'a K, &'a V, Option<Duration>)>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Snapshot<K, V> {
+    capacity: usize,
+    entries: Vec<(K, V, Option<Duration>)>,
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    pub(crate) fn snapshot_ref(&self) -> SnapshotRef<'_, K, V> {
+        SnapshotRef {
+            capacity: self.capacity,
+            entries: self.snapshot_entries(),
+        }
+    }
+
+    /// Кеш из представления; `elapsed` - сколько прошло с момента его создания,
+    /// элементы, истёкшие за это время, пропускаются
+    pub(crate) fn from_snapshot(snapshot: Snapshot<K, V>, elapsed: Duration) -> Self
+    where
+        S: Default,
+    {
+        let map = HashMap::with_capacity_and_hasher(snapshot.entries.len(), S::default());
+        let mut cache = LruCache::from_map(map, snapshot.capacity, CleanupMode::OnAccess);
+        let entries = snapshot
+            .entries
+            .into_iter()
+            .rev()
+            .filter_map(|(key, value, ttl)| {
+                let ttl = match ttl {
+                    None => Duration::MAX,
+                    Some(ttl) if ttl > elapsed => ttl - elapsed,
+                    Some(_) => return None,
+                };
+                Some((key, value, Some(ttl)))
+            });
+        cache.put_all(entries);
+        cache
+    }
+}
+
+/// Сериализует непросроченные элементы в порядке LRU с оставшимися TTL и ёмкость
+///
+/// Функция веса, слушатели и остальные параметры не сохраняются.
+impl<K, V, S> Serialize for LruCache<K, V, S>
+where
+    K: Eq + Hash + Serialize,
+    V: Serialize,
+    S: BuildHasher,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.snapshot_ref().serialize(serializer)
+    }
+}
+
+/// Восстанавливает кеш с тем же порядком LRU; TTL отсчитываются от момента десериализации,
+/// режим очистки [`CleanupMode::OnAccess`]
+impl<'de, K, V, S> Deserialize<'de> for LruCache<K, V, S>
+where
+    K: Eq + Hash + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let snapshot = Snapshot::deserialize(deserializer)?;
+        Ok(LruCache::from_snapshot(snapshot, Duration::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockClock, Ttl};
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder().capacity(3).clock(clock.clone()).build();
+        cache.put("a".to_string(), 1, Some(Duration::from_secs(10)));
+        cache.put("b".to_string(), 2, None);
+        cache.put("c".to_string(), 3, Some(Duration::from_secs(1)));
+        cache.get("a");
+        clock.advance(Duration::from_secs(2));
+
+        let json = serde_json::to_string(&cache).unwrap();
+        assert_eq!(
+            json,
+            r#"{"capacity":3,"entries":[["a",1,{"secs":8,"nanos":0}],["b",2,null]]}"#
+        );
+
+        let restored: LruCache<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.capacity(), 3);
+        assert!(restored.eq_ordered(&cache));
+        assert!(
+            matches!(restored.ttl("a"), Some(Ttl::Remaining(ttl)) if ttl <= Duration::from_secs(8))
+        );
+        assert_eq!(restored.ttl("b"), Some(Ttl::Persistent));
+    }
+}
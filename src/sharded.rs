@@ -0,0 +1,374 @@
+use std::borrow::Borrow;
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::buffer::BufferedCache;
+use crate::flight::InFlight;
+use crate::{CacheStats, CleanupMode, DefaultHashBuilder, LruCache, Ttl};
+
+const POISONED: &str = "сегмент кеша отравлен паникой в другом потоке";
+
+struct Shards<K, V, S> {
+    shards: Box<[BufferedCache<K, V, S>]>,
+    hasher: S,
+    loading: InFlight<K, V>,
+}
+
+/// Состояние одного сегмента, см. [`ShardedLruCache::shard_stats`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShardStats {
+    pub len: usize,
+    pub stats: CacheStats,
+    /// Сколько раз запись ждала блокировку сегмента, занятую другим потоком
+    pub contention: u64,
+}
+
+/// Потокобезопасный кеш из независимых сегментов со своими блокировками
+///
+/// Ключи распределяются по сегментам по хешу, ёмкость делится между ними поровну,
+/// поэтому вытеснение LRU выполняется внутри сегмента, а не по всему кешу.
+/// Потоки, работающие с разными сегментами, не ждут друг друга, а чтения
+/// не ждут друг друга и внутри сегмента, как в [`SyncLruCache`](crate::SyncLruCache).
+/// Копии (`clone`) указывают на один и тот же кеш.
+pub struct ShardedLruCache<K, V, S = DefaultHashBuilder> {
+    inner: Arc<Shards<K, V, S>>,
+}
+
+impl<K, V, S> Clone for ShardedLruCache<K, V, S> {
+    fn clone(&self) -> Self {
+        ShardedLruCache {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> ShardedLruCache<K, V> {
+    /// Кеш с числом сегментов, равным учетверённому числу ядер
+    pub fn new(capacity: usize, cleanup_mode: CleanupMode) -> Self {
+        let shards = thread::available_parallelism().map_or(1, usize::from) * 4;
+        Self::with_shards(capacity, shards, cleanup_mode)
+    }
+
+    pub fn with_shards(capacity: usize, shards: usize, cleanup_mode: CleanupMode) -> Self {
+        Self::with_hasher(
+            capacity,
+            shards,
+            cleanup_mode,
+            DefaultHashBuilder::default(),
+        )
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> ShardedLruCache<K, V, S> {
+    /// Кеш из `shards` сегментов; сегментов не бывает больше ёмкости,
+    /// чтобы каждый мог хранить хотя бы один элемент
+    pub fn with_hasher(
+        capacity: usize,
+        shards: usize,
+        cleanup_mode: CleanupMode,
+        hasher: S,
+    ) -> Self {
+        let count = shards.clamp(1, capacity.max(1));
+        let shards = (0..count)
+            .map(|i| {
+                // остаток от деления достаётся первым сегментам
+                let slice = if capacity == usize::MAX {
+                    usize::MAX
+                } else {
+                    capacity / count + usize::from(i < capacity % count)
+                };
+                BufferedCache::new(LruCache::with_hasher(slice, cleanup_mode, hasher.clone()))
+            })
+            .collect();
+
+        ShardedLruCache {
+            inner: Arc::new(Shards {
+                shards,
+                hasher,
+                loading: InFlight::default(),
+            }),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> ShardedLruCache<K, V, S> {
+    pub fn shard_count(&self) -> usize {
+        self.inner.shards.len()
+    }
+
+    fn shard_index<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        // перемешивание Фибоначчи: сегмент не должен зависеть от младших битов хеша,
+        // по которым карта внутри сегмента выбирает корзину
+        let mixed = self
+            .inner
+            .hasher
+            .hash_one(key)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        ((mixed as u128 * self.inner.shards.len() as u128) >> 64) as usize
+    }
+
+    pub(crate) fn lock_index(&self, index: usize) -> RwLockWriteGuard<'_, LruCache<K, V, S>> {
+        self.inner.shards[index].write().expect(POISONED)
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &BufferedCache<K, V, S> {
+        &self.inner.shards[self.shard_index(key)]
+    }
+
+    fn shared_shard<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockReadGuard<'_, LruCache<K, V, S>> {
+        self.shard(key).shared().expect(POISONED)
+    }
+
+    /// Блокирует сегмент, которому принадлежит ключ, см. [`SyncLruCache::lock`](crate::SyncLruCache::lock)
+    pub fn lock_shard<Q>(&self, key: &Q) -> RwLockWriteGuard<'_, LruCache<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock_index(self.shard_index(key))
+    }
+
+    fn for_each_shard(&self, mut f: impl FnMut(&mut LruCache<K, V, S>)) {
+        for index in 0..self.inner.shards.len() {
+            f(&mut self.lock_index(index));
+        }
+    }
+
+    pub fn put(&self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        self.lock_shard(&key).put(key, value, ttl)
+    }
+
+    pub fn push(&self, key: K, value: V, ttl: Option<Duration>) -> Option<(K, V)> {
+        self.lock_shard(&key).push(key, value, ttl)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.with_value(key, V::clone)
+    }
+
+    pub fn with_value<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).read(key, f).expect(POISONED)
+    }
+
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shared_shard(key).peek(key).cloned()
+    }
+
+    /// Возвращает элемент, а при его отсутствии вычисляет и сохраняет;
+    /// `f` выполняется под блокировкой сегмента
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&self, key: K, f: F) -> V
+    where
+        V: Clone,
+    {
+        if let Some(value) = self.shard(&key).read_hit(&key, V::clone).expect(POISONED) {
+            return value;
+        }
+        self.lock_shard(&key).get_or_insert_with(key, f).clone()
+    }
+
+    /// Загрузка без блокировки сегмента, см. [`SyncLruCache::get_with`](crate::SyncLruCache::get_with)
+    pub fn get_with<F: FnOnce() -> V>(&self, key: K, f: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+    {
+        match self.try_get_with(key, || Ok::<_, Infallible>(f())) {
+            Ok(value) => value,
+        }
+    }
+
+    /// Вариант `get_with` для загрузки с ошибкой: ошибка возвращается загрузившему
+    /// потоку, а ожидавшие потоки повторяют загрузку сами
+    pub fn try_get_with<F, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+        K: Clone,
+        V: Clone,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        self.inner.loading.try_run(&key, || {
+            // предыдущая загрузка могла завершиться между промахом и регистрацией
+            if let Some(value) = self.peek(&key) {
+                return Ok(value);
+            }
+            let value = f()?;
+            self.put(key.clone(), value.clone(), None);
+            Ok(value)
+        })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared_shard(key).contains_key(key)
+    }
+
+    pub fn ttl<Q>(&self, key: &Q) -> Option<Ttl>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared_shard(key).ttl(key)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock_shard(key).remove(key)
+    }
+
+    /// Применяет [`LruCache::retain`] к сегментам по очереди
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&self, mut f: F) {
+        self.for_each_shard(|shard| shard.retain(&mut f));
+    }
+
+    pub fn evict_expired(&self) {
+        self.for_each_shard(LruCache::evict_expired);
+    }
+
+    pub fn clear(&self) {
+        self.for_each_shard(LruCache::clear);
+    }
+
+    /// Суммарное число элементов; сегменты блокируются по очереди,
+    /// поэтому при одновременных изменениях результат приблизителен
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        self.for_each_shard(|shard| len += shard.len());
+        len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Суммарная ёмкость сегментов
+    pub fn capacity(&self) -> usize {
+        let mut capacity = 0usize;
+        self.for_each_shard(|shard| capacity = capacity.saturating_add(shard.capacity()));
+        capacity
+    }
+
+    /// Статистика, просуммированная по сегментам
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats::default();
+        self.for_each_shard(|shard| stats.merge(&shard.stats()));
+        stats
+    }
+
+    /// Число элементов, статистика и конкуренция за блокировку по каждому сегменту,
+    /// чтобы находить горячие сегменты и неравномерное распределение ключей
+    pub fn shard_stats(&self) -> Vec<ShardStats> {
+        (0..self.inner.shards.len())
+            .map(|index| {
+                let shard = self.lock_index(index);
+                ShardStats {
+                    len: shard.len(),
+                    stats: shard.stats(),
+                    contention: self.inner.shards[index].contention(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sharded_cache() {
+        let cache = ShardedLruCache::with_shards(64, 8, CleanupMode::OnAccess);
+        assert_eq!(cache.shard_count(), 8);
+        assert_eq!(cache.capacity(), 64);
+
+        let threads: Vec<_> = (0..4u32)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        cache.put(t * 1000 + i, i, None);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // каждый сегмент заполнен до своей доли ёмкости
+        assert_eq!(cache.len(), 64);
+        cache.put(5000, 1, None);
+        assert_eq!(cache.get(&5000), Some(1));
+        assert_eq!(cache.get(&5001), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.insertions), (1, 1, 4001));
+        assert_eq!(stats.evictions, 4001 - 64);
+
+        let small = ShardedLruCache::<u32, u32>::with_shards(3, 16, CleanupMode::OnDemand);
+        assert_eq!(small.shard_count(), 3);
+    }
+
+    #[test]
+    fn test_shard_stats() {
+        let cache = ShardedLruCache::with_shards(64, 2, CleanupMode::OnAccess);
+        let (hot, cold): (Vec<u32>, Vec<u32>) =
+            (0..40).partition(|key| cache.shard_index(key) == 0);
+        for &key in &hot[..10] {
+            cache.put(key, key, None);
+            cache.get(&key);
+        }
+        cache.put(cold[0], 0, None);
+        cache.get(&cold[1]);
+
+        // запись ждёт, пока удерживается блокировка горячего сегмента
+        let guard = cache.lock_index(0);
+        let writer = {
+            let cache = cache.clone();
+            let key = hot[10];
+            thread::spawn(move || cache.put(key, key, None))
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while cache.inner.shards[0].contention() == 0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "запись не дождалась блокировки"
+            );
+            thread::yield_now();
+        }
+        drop(guard);
+        writer.join().unwrap();
+
+        let shards = cache.shard_stats();
+        assert_eq!(shards[0].len, 11);
+        assert_eq!((shards[0].stats.hits, shards[0].stats.misses), (10, 0));
+        assert_eq!(shards[0].contention, 1);
+        assert_eq!(shards[1].len, 1);
+        assert_eq!((shards[1].stats.hits, shards[1].stats.misses), (0, 1));
+        assert_eq!(shards[1].contention, 0);
+    }
+}
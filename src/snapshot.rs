@@ -0,0 +1,185 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::LruCache;
+
+const MAGIC: &[u8; 4] = b"LRUC";
+const VERSION: u16 = 2;
+
+/// Заголовок файла: сигнатура, версия формата, время сохранения (наносекунды Unix),
+/// длина и CRC-32 данных
+const HEADER_LEN: usize = MAGIC.len() + 2 + 8 + 8 + 4;
+
+/// Ошибка хранения на диске: снимка ([`LruCache::save_to`]), журнала или хранилища
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// Файл не является снимком кеша
+    NotSnapshot,
+    /// Снимок записан другой версией формата
+    UnsupportedVersion(u16),
+    /// Снимок повреждён: данные обрезаны или не совпала контрольная сумма
+    Corrupted,
+    /// Элементы не удалось закодировать или декодировать
+    Encoding(bincode::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "ошибка ввода-вывода снимка: {error}"),
+            SnapshotError::NotSnapshot => f.write_str("файл не является снимком кеша"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "неподдерживаемая версия снимка {version}")
+            }
+            SnapshotError::Corrupted => f.write_str("снимок кеша повреждён"),
+            SnapshotError::Encoding(error) => write!(f, "ошибка кодирования снимка: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotError::Io(error) => Some(error),
+            SnapshotError::Encoding(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Eq + Hash + Serialize,
+    V: Serialize,
+    S: BuildHasher,
+{
+    /// Сохраняет элементы в порядке LRU с оставшимися TTL в файл, см. [`LruCache::load_from`]
+    ///
+    /// После заголовка с версией формата, временем сохранения и контрольной суммой следует
+    /// представление кеша в bincode, как при сериализации через serde. Снимок пишется
+    /// во временный файл рядом и после `fsync` атомарно переименовывается, поэтому
+    /// сбой посреди записи не портит прежний снимок.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let payload = bincode::serialize(&self.snapshot_ref()).map_err(SnapshotError::Encoding)?;
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let temp = path.with_file_name(file_name);
+        let written = (|| {
+            let mut file = File::create(&temp)?;
+            file.write_all(MAGIC)?;
+            file.write_all(&VERSION.to_le_bytes())?;
+            file.write_all(&saved_at.to_le_bytes())?;
+            file.write_all(&(payload.len() as u64).to_le_bytes())?;
+            file.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+            file.write_all(&payload)?;
+            file.sync_all()
+        })();
+        if let Err(error) = written.and_then(|()| fs::rename(&temp, path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(error.into());
+        }
+
+        // переименование становится надёжным только после сброса каталога
+        #[cfg(unix)]
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Eq + Hash + DeserializeOwned,
+    V: DeserializeOwned,
+    S: BuildHasher + Default,
+{
+    /// Восстанавливает кеш, сохранённый [`LruCache::save_to`], проверяя контрольную сумму
+    ///
+    /// Время, прошедшее с сохранения по системным часам, вычитается из TTL, поэтому
+    /// элементы, истёкшие за время простоя, не загружаются.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let data = fs::read(path)?;
+        if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+            return Err(SnapshotError::NotSnapshot);
+        }
+        let (header, payload) = data
+            .split_at_checked(HEADER_LEN)
+            .ok_or(SnapshotError::Corrupted)?;
+
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let saved_at = u64::from_le_bytes(header[6..14].try_into().unwrap());
+        let len = u64::from_le_bytes(header[14..22].try_into().unwrap());
+        let checksum = u32::from_le_bytes(header[22..26].try_into().unwrap());
+        if payload.len() as u64 != len || crc32fast::hash(payload) != checksum {
+            return Err(SnapshotError::Corrupted);
+        }
+
+        let snapshot = bincode::deserialize(payload).map_err(SnapshotError::Encoding)?;
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH + Duration::from_nanos(saved_at))
+            .unwrap_or_default();
+        Ok(LruCache::from_snapshot(snapshot, elapsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, Ttl};
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("lru-snapshot-{}", std::process::id()));
+        let mut cache = LruCache::new(10, CleanupMode::OnAccess);
+        cache.put(1u32, "one".to_string(), Some(Duration::from_secs(60)));
+        cache.put(2, "two".to_string(), None);
+        cache.put(3, "gone".to_string(), Some(Duration::from_millis(1)));
+        std::thread::sleep(Duration::from_millis(5));
+        cache.save_to(&path).unwrap();
+
+        let restored: LruCache<u32, String> = LruCache::load_from(&path).unwrap();
+        assert!(restored.eq_ordered(&cache));
+        assert_eq!(restored.capacity(), 10);
+        assert!(
+            matches!(restored.ttl(&1), Some(Ttl::Remaining(ttl)) if ttl <= Duration::from_secs(60))
+        );
+
+        // повреждённые данные и обрезанный файл отвергаются
+        let mut data = fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 1;
+        fs::write(&path, &data).unwrap();
+        let error = LruCache::<u32, String>::load_from(&path).unwrap_err();
+        assert!(matches!(error, SnapshotError::Corrupted));
+        fs::write(&path, &data[..HEADER_LEN - 1]).unwrap();
+        let error = LruCache::<u32, String>::load_from(&path).unwrap_err();
+        assert!(matches!(error, SnapshotError::Corrupted));
+        fs::write(&path, b"not a cache snapshot").unwrap();
+        let error = LruCache::<u32, String>::load_from(&path).unwrap_err();
+        assert!(matches!(error, SnapshotError::NotSnapshot));
+        fs::remove_file(&path).unwrap();
+    }
+}
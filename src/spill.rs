@@ -0,0 +1,259 @@
+use std::fs;
+use std::hash::{BuildHasher, Hash};
+use std::io;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{CacheTier, DefaultHashBuilder, LruCache, SnapshotError};
+
+/// Хранилище элементов на диске: каталог с файлом на каждый ключ
+///
+/// Имя файла - 64-битный хеш FNV-1a закодированного ключа, сам ключ хранится в файле
+/// и сверяется при чтении, поэтому при совпадении хешей более поздняя запись просто
+/// вытесняет раннюю. Файл, повреждённый при сбое (не совпала CRC-32), считается промахом.
+pub struct DiskStore<K, V> {
+    dir: PathBuf,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> DiskStore<K, V>
+where
+    K: Serialize + DeserializeOwned + PartialEq,
+    V: Serialize + DeserializeOwned,
+{
+    /// Открывает каталог `dir`, создавая его при отсутствии
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskStore {
+            dir,
+            marker: PhantomData,
+        })
+    }
+
+    /// Записывает элемент со временем жизни `ttl` (`None` - без ограничения)
+    pub fn put(&self, key: &K, value: &V, ttl: Option<Duration>) -> Result<(), SnapshotError> {
+        let expires_at = ttl.and_then(|ttl| unix_now().checked_add(ttl.as_nanos() as u64));
+        let payload =
+            bincode::serialize(&(key, value, expires_at)).map_err(SnapshotError::Encoding)?;
+        let mut data = Vec::with_capacity(4 + payload.len());
+        data.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        data.extend_from_slice(&payload);
+        fs::write(self.path(key)?, data)?;
+        Ok(())
+    }
+
+    /// Значение с оставшимся временем жизни; просроченный элемент удаляется
+    pub fn get(&self, key: &K) -> Result<Option<(V, Option<Duration>)>, SnapshotError> {
+        let path = self.path(key)?;
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let entry = data.split_at_checked(4).and_then(|(checksum, payload)| {
+            (crc32fast::hash(payload).to_le_bytes() == checksum)
+                .then(|| bincode::deserialize::<(K, V, Option<u64>)>(payload).ok())
+                .flatten()
+        });
+        let Some((stored, value, expires_at)) = entry else {
+            remove_file(&path)?;
+            return Ok(None);
+        };
+        if stored != *key {
+            return Ok(None);
+        }
+
+        let now = unix_now();
+        match expires_at {
+            None => Ok(Some((value, None))),
+            Some(expires_at) if expires_at > now => {
+                Ok(Some((value, Some(Duration::from_nanos(expires_at - now)))))
+            }
+            Some(_) => {
+                remove_file(&path)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Извлекает элемент, удаляя его из хранилища
+    pub fn take(&self, key: &K) -> Result<Option<(V, Option<Duration>)>, SnapshotError> {
+        let entry = self.get(key)?;
+        if entry.is_some() {
+            remove_file(&self.path(key)?)?;
+        }
+        Ok(entry)
+    }
+
+    /// Удаляет элемент; возвращает `true`, если он был
+    pub fn remove(&self, key: &K) -> Result<bool, SnapshotError> {
+        Ok(self.take(key)?.is_some())
+    }
+
+    /// Удаляет все элементы хранилища
+    pub fn clear(&self) -> Result<(), SnapshotError> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "entry")
+            {
+                remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self, key: &K) -> Result<PathBuf, SnapshotError> {
+        let key = bincode::serialize(key).map_err(SnapshotError::Encoding)?;
+        // FNV-1a: имя файла не должно зависеть от случайного зерна хешера
+        let hash = key.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        Ok(self.dir.join(format!("{hash:016x}.entry")))
+    }
+}
+
+/// `DiskStore` как нижний уровень [`TieredCache`](crate::TieredCache): ошибки ввода-вывода
+/// считаются промахами
+impl<K, V> CacheTier<K, V> for DiskStore<K, V>
+where
+    K: Serialize + DeserializeOwned + PartialEq,
+    V: Serialize + DeserializeOwned,
+{
+    fn get(&mut self, key: &K) -> Option<(V, Option<Duration>)> {
+        DiskStore::get(self, key).ok().flatten()
+    }
+
+    fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Vec<(K, V, Option<Duration>)> {
+        let _ = DiskStore::put(self, &key, &value, ttl);
+        Vec::new()
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.take(key).ok().flatten().map(|(value, _)| value)
+    }
+}
+
+/// Двухуровневый кеш: элементы, вытесненные из памяти по ёмкости, записываются
+/// в [`DiskStore`], а промах в памяти проверяет диск и возвращает найденный элемент в память
+///
+/// Подходит для значений, которые дорого вычислять заново. Вытесненные элементы
+/// слушателю удалений не передаются, оставшийся TTL сохраняется на диске.
+pub struct SpilloverCache<K, V, S = DefaultHashBuilder> {
+    cache: LruCache<K, V, S>,
+    store: DiskStore<K, V>,
+}
+
+impl<K, V, S> SpilloverCache<K, V, S>
+where
+    K: Eq + Hash + Clone + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    S: BuildHasher,
+{
+    /// Кеш в памяти `cache` с вытеснением в каталог `dir`
+    pub fn new(mut cache: LruCache<K, V, S>, dir: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        cache.capture_evictions();
+        Ok(SpilloverCache {
+            cache,
+            store: DiskStore::open(dir)?,
+        })
+    }
+
+    /// Добавление элемента в память, см. [`LruCache::put`]; прежняя копия на диске удаляется
+    pub fn put(
+        &mut self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<Option<V>, SnapshotError> {
+        self.store.remove(&key)?;
+        let old = self.cache.put(key, value, ttl);
+        self.spill()?;
+        Ok(old)
+    }
+
+    /// Чтение из памяти, а при промахе - с диска с переносом элемента обратно в память
+    pub fn get(&mut self, key: &K) -> Result<Option<&V>, SnapshotError> {
+        if !self.cache.contains_key(key)
+            && let Some((value, ttl)) = self.store.take(key)?
+        {
+            self.cache
+                .put(key.clone(), value, Some(ttl.unwrap_or(Duration::MAX)));
+            self.spill()?;
+        }
+        Ok(self.cache.get(key))
+    }
+
+    /// Удаление из памяти и с диска
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, SnapshotError> {
+        let value = self.cache.remove(key);
+        let stored = self.store.take(key)?;
+        Ok(value.or(stored.map(|(value, _)| value)))
+    }
+
+    pub fn cache(&self) -> &LruCache<K, V, S> {
+        &self.cache
+    }
+
+    pub fn store(&self) -> &DiskStore<K, V> {
+        &self.store
+    }
+
+    fn spill(&mut self) -> Result<(), SnapshotError> {
+        for (key, value, ttl) in self.cache.take_evicted() {
+            self.store.put(&key, &value, ttl)?;
+        }
+        Ok(())
+    }
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_spillover() {
+        let dir = std::env::temp_dir().join(format!("lru-spill-{}", std::process::id()));
+        let mut cache = SpilloverCache::new(LruCache::new(2, CleanupMode::OnAccess), &dir).unwrap();
+        cache.put(1u32, "one".to_string(), None).unwrap();
+        cache
+            .put(2, "two".to_string(), Some(Duration::from_secs(60)))
+            .unwrap();
+        cache.put(3, "three".to_string(), None).unwrap();
+        assert!(!cache.cache().contains_key(&1));
+        assert_eq!(cache.cache().stats().evictions, 1);
+
+        // промах в памяти находит элемент на диске и вытесняет туда "2"
+        assert_eq!(cache.get(&1).unwrap().map(String::as_str), Some("one"));
+        assert!(cache.store().get(&1).unwrap().is_none());
+        let (value, ttl) = cache.store().get(&2).unwrap().unwrap();
+        assert_eq!(value, "two");
+        assert!(ttl.is_some_and(|ttl| ttl <= Duration::from_secs(60)));
+
+        assert_eq!(cache.remove(&2).unwrap().as_deref(), Some("two"));
+        assert_eq!(cache.get(&2).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
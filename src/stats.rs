@@ -0,0 +1,245 @@
+use std::time::{Duration, Instant};
+
+use crate::RemovalCause;
+
+/// Период полураспада недавней доли попаданий по умолчанию
+pub(crate) const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(60);
+
+/// Снимок статистики кеша, см. [`LruCache::stats`](crate::LruCache::stats)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CacheStats {
+    /// Чтения, нашедшие непросроченный элемент
+    pub hits: u64,
+    /// Чтения без результата
+    pub misses: u64,
+    /// Добавленные новые ключи
+    pub insertions: u64,
+    /// Элементы, вытесненные из-за превышения ёмкости
+    pub evictions: u64,
+    /// Элементы, удалённые по истечении времени жизни
+    pub expirations: u64,
+    // затухающие суммы попаданий и всех чтений, см. [`CacheStats::recent_hit_rate`]
+    recent_hits: f64,
+    recent_total: f64,
+}
+
+impl CacheStats {
+    /// Доля попаданий за всё время; `None`, если чтений не было
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+
+    /// Доля попаданий с экспоненциальным затуханием: чтения старше периода полураспада
+    /// (по умолчанию минута, см. [`LruCacheBuilder::stats_half_life`](crate::LruCacheBuilder::stats_half_life))
+    /// весят вдвое меньше, поэтому резкое падение заметно сразу
+    pub fn recent_hit_rate(&self) -> Option<f64> {
+        (self.recent_total > 0.0).then(|| self.recent_hits / self.recent_total)
+    }
+
+    /// Суммирует статистику нескольких кешей (сегментов)
+    pub(crate) fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.insertions += other.insertions;
+        self.evictions += other.evictions;
+        self.expirations += other.expirations;
+        self.recent_hits += other.recent_hits;
+        self.recent_total += other.recent_total;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Counters {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) insertions: u64,
+    pub(crate) evictions: u64,
+    pub(crate) expirations: u64,
+}
+
+/// Счётчики кеша; все изменения выполняются под `&mut LruCache`
+#[derive(Clone)]
+pub(crate) struct Stats {
+    // счётчики за всё время; `stats_snapshot` сдвигает только `base`,
+    // поэтому экспорт метрик остаётся монотонным
+    pub(crate) total: Counters,
+    base: Counters,
+    recent: DecayedRate,
+}
+
+impl Stats {
+    pub(crate) fn new(half_life: Duration, now: Instant) -> Self {
+        Stats {
+            total: Counters::default(),
+            base: Counters::default(),
+            recent: DecayedRate::new(half_life, now),
+        }
+    }
+
+    pub(crate) fn record_hit(&mut self, now: Instant) {
+        self.total.hits += 1;
+        self.recent.record(true, now);
+    }
+
+    pub(crate) fn record_miss(&mut self, now: Instant) {
+        self.total.misses += 1;
+        self.recent.record(false, now);
+    }
+
+    pub(crate) fn record_insertion(&mut self) {
+        self.total.insertions += 1;
+    }
+
+    pub(crate) fn record_removal(&mut self, cause: RemovalCause) {
+        match cause {
+            RemovalCause::SizeEvicted => self.total.evictions += 1,
+            RemovalCause::Expired => self.total.expirations += 1,
+            RemovalCause::Replaced | RemovalCause::Explicit => {}
+        }
+    }
+
+    pub(crate) fn snapshot(&self, now: Instant) -> CacheStats {
+        let (total, base) = (&self.total, &self.base);
+        let (recent_hits, recent_total) = self.recent.sums(now);
+        CacheStats {
+            hits: total.hits - base.hits,
+            misses: total.misses - base.misses,
+            insertions: total.insertions - base.insertions,
+            evictions: total.evictions - base.evictions,
+            expirations: total.expirations - base.expirations,
+            recent_hits,
+            recent_total,
+        }
+    }
+
+    /// Снимок со сбросом счётчиков; недавняя доля попаданий сама затухает и не сбрасывается
+    pub(crate) fn take(&mut self, now: Instant) -> CacheStats {
+        let snapshot = self.snapshot(now);
+        self.base = self.total;
+        snapshot
+    }
+}
+
+/// Экспоненциально затухающие попадания и промахи
+///
+/// Затухание применяется шагами по 1/8 периода полураспада: чтения внутри шага
+/// копятся целыми числами, и `powf` вызывается не чаще раза за шаг.
+#[derive(Clone)]
+struct DecayedRate {
+    tick: Duration,
+    // множитель затухания за один шаг
+    decay: f64,
+    tick_start: Instant,
+    hits: f64,
+    misses: f64,
+    tick_hits: u64,
+    tick_misses: u64,
+}
+
+impl DecayedRate {
+    fn new(half_life: Duration, now: Instant) -> Self {
+        assert!(!half_life.is_zero());
+        DecayedRate {
+            tick: half_life / 8,
+            decay: 0.5f64.powf(1.0 / 8.0),
+            tick_start: now,
+            hits: 0.0,
+            misses: 0.0,
+            tick_hits: 0,
+            tick_misses: 0,
+        }
+    }
+
+    fn record(&mut self, hit: bool, now: Instant) {
+        self.advance(now);
+        if hit {
+            self.tick_hits += 1;
+        } else {
+            self.tick_misses += 1;
+        }
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.tick_start);
+        if elapsed < self.tick {
+            return;
+        }
+
+        let ticks = elapsed.as_nanos() / self.tick.as_nanos().max(1);
+        let ticks = u32::try_from(ticks).unwrap_or(u32::MAX);
+        let decay = self.decay.powi(ticks.min(i32::MAX as u32) as i32);
+        self.hits = (self.hits + self.tick_hits as f64) * decay;
+        self.misses = (self.misses + self.tick_misses as f64) * decay;
+        self.tick_hits = 0;
+        self.tick_misses = 0;
+        self.tick_start = self
+            .tick
+            .checked_mul(ticks)
+            .and_then(|passed| self.tick_start.checked_add(passed))
+            .unwrap_or(now);
+    }
+
+    /// Затухающие суммы попаданий и всех чтений на момент `now`
+    fn sums(&self, now: Instant) -> (f64, f64) {
+        let mut rate = self.clone();
+        rate.advance(now);
+
+        let hits = rate.hits + rate.tick_hits as f64;
+        (hits, hits + rate.misses + rate.tick_misses as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CleanupMode, LruCache, MockClock};
+    use std::time::Duration;
+
+    #[test]
+    fn test_stats() {
+        let clock = MockClock::new();
+        let mut cache = LruCache::builder()
+            .capacity(2)
+            .cleanup_mode(CleanupMode::OnDemand)
+            .clock(clock.clone())
+            .build();
+
+        cache.put("a", 1, Some(Duration::from_secs(1)));
+        cache.put("b", 2, None);
+        cache.put("c", 3, None);
+        for _ in 0..9 {
+            cache.get(&"b");
+        }
+        cache.get(&"a");
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (9, 1));
+        assert_eq!((stats.insertions, stats.evictions), (3, 1));
+        assert_eq!(stats.hit_rate(), Some(0.9));
+        assert_eq!(stats.recent_hit_rate(), Some(0.9));
+
+        // после деплоя всё промахивается: недавняя доля падает, общая почти нет
+        clock.advance(Duration::from_secs(600));
+        cache.get(&"x");
+        let stats = cache.stats();
+        assert!(stats.hit_rate().unwrap() > 0.8);
+        assert!(stats.recent_hit_rate().unwrap() < 0.1);
+    }
+
+    #[test]
+    fn test_stats_snapshot() {
+        let mut cache = LruCache::new(1, CleanupMode::OnDemand);
+        cache.put("a", 1, None);
+        cache.put("b", 2, None);
+        cache.get(&"b");
+
+        let first = cache.stats_snapshot();
+        assert_eq!((first.hits, first.insertions, first.evictions), (1, 2, 1));
+
+        cache.get(&"a");
+        let second = cache.stats_snapshot();
+        assert_eq!((second.hits, second.misses, second.insertions), (0, 1, 0));
+        assert_eq!(second.recent_hit_rate(), Some(0.5));
+        assert_eq!(cache.stats().misses, 0);
+    }
+}
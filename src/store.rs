@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "sled")]
+use std::marker::PhantomData;
+#[cfg(feature = "sled")]
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "sled")]
+use serde::Serialize;
+#[cfg(feature = "sled")]
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "sled")]
+use crate::SnapshotError;
+use crate::{DefaultHashBuilder, LruCache};
+
+/// Постоянное хранилище под кешем, см. [`BackedCache`]
+///
+/// Методы принимают `&self`, как у встраиваемых хранилищ вроде sled или RocksDB,
+/// которые сами синхронизируют доступ.
+pub trait BackingStore<K, V> {
+    type Error;
+
+    fn load(&self, key: &K) -> Result<Option<V>, Self::Error>;
+
+    fn store(&self, key: &K, value: &V) -> Result<(), Self::Error>;
+
+    fn delete(&self, key: &K) -> Result<(), Self::Error>;
+}
+
+/// Когда [`BackedCache`] записывает элементы в хранилище
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+    /// При уходе элемента из кеша по ёмкости или TTL и при `flush`
+    #[default]
+    OnEvict,
+    /// Сквозная запись: `put` сначала записывает элемент в хранилище и только затем в кеш,
+    /// поэтому хранилище всегда актуально
+    Through,
+    /// Отложенная запись: `put` меняет только кеш и помечает элемент изменённым,
+    /// изменённые элементы записываются пачкой при `flush`, при уходе из кеша
+    /// и когда их становится больше `max_dirty`
+    Behind { max_dirty: usize },
+}
+
+/// Ограниченный по ёмкости набор горячих элементов поверх большего постоянного хранилища
+///
+/// Промах загружает элемент из хранилища. В режиме [`WriteMode::OnEvict`] элементы,
+/// покидающие кеш по ёмкости или по истечении TTL, записываются в хранилище, поэтому TTL
+/// означает время пребывания в памяти, а не срок годности данных; такие элементы слушателю
+/// удалений не передаются. `remove` удаляет элемент и из хранилища, `flush` записывает
+/// всё содержимое кеша (например, перед остановкой).
+pub struct BackedCache<K, V, B, S = DefaultHashBuilder> {
+    cache: LruCache<K, V, S>,
+    store: B,
+    mode: WriteMode,
+    dirty: HashSet<K>,
+}
+
+impl<K, V, B, S> BackedCache<K, V, B, S>
+where
+    K: Eq + Hash + Clone,
+    B: BackingStore<K, V>,
+    S: BuildHasher,
+{
+    /// Кеш в режиме [`WriteMode::OnEvict`]
+    pub fn new(cache: LruCache<K, V, S>, store: B) -> Self {
+        Self::with_mode(cache, store, WriteMode::OnEvict)
+    }
+
+    pub fn with_mode(mut cache: LruCache<K, V, S>, store: B, mode: WriteMode) -> Self {
+        if mode != WriteMode::Through {
+            cache.capture_expirations();
+        }
+        BackedCache {
+            cache,
+            store,
+            mode,
+            dirty: HashSet::new(),
+        }
+    }
+
+    /// Чтение из кеша, а при промахе - из хранилища с сохранением в кеше
+    pub fn get(&mut self, key: &K) -> Result<Option<&V>, B::Error> {
+        if !self.cache.contains_key(key) {
+            // просроченное значение новее сохранённого: сначала оно записывается в хранилище
+            self.cache.remove(key);
+            self.demote()?;
+        }
+        if !self.cache.contains_key(key)
+            && let Some(value) = self.store.load(key)?
+        {
+            self.cache.put(key.clone(), value, None);
+            self.demote()?;
+        }
+        Ok(self.cache.get(key))
+    }
+
+    /// Добавление элемента в кеш; в хранилище он попадёт сразу в режиме [`WriteMode::Through`],
+    /// иначе при вытеснении или `flush`. Ошибка хранилища при сквозной записи оставляет кеш прежним.
+    pub fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Result<Option<V>, B::Error> {
+        if self.mode == WriteMode::Through {
+            self.store.store(&key, &value)?;
+        }
+        let WriteMode::Behind { max_dirty } = self.mode else {
+            let old = self.cache.put(key, value, ttl);
+            self.demote()?;
+            return Ok(old);
+        };
+
+        let old = self.cache.put(key.clone(), value, ttl);
+        self.demote()?;
+        // отключённый кеш элемент не хранит, и записывать будет нечего
+        if self.cache.contains_key(&key) {
+            self.dirty.insert(key);
+        }
+        if self.dirty.len() > max_dirty {
+            self.write_dirty()?;
+        }
+        Ok(old)
+    }
+
+    /// Удаление из хранилища и кеша
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, B::Error> {
+        self.store.delete(key)?;
+        self.dirty.remove(key);
+        let old = self.cache.remove(key);
+        // просроченный элемент перехвачен для записи, но в хранилище он возвращаться не должен
+        let demoted = self
+            .cache
+            .take_demoted()
+            .into_iter()
+            .filter(|(demoted, _)| demoted != key)
+            .collect();
+        self.store_demoted(demoted)?;
+        Ok(old)
+    }
+
+    /// Записывает в хранилище всё содержимое кеша, при отложенной записи - только
+    /// изменённые элементы; при сквозной записи ничего не делает
+    ///
+    /// В режиме [`WriteMode::Behind`] предназначен для периодического вызова из фоновой задачи.
+    pub fn flush(&mut self) -> Result<(), B::Error> {
+        match self.mode {
+            WriteMode::Through => Ok(()),
+            WriteMode::Behind { .. } => self.write_dirty(),
+            WriteMode::OnEvict => {
+                self.demote()?;
+                for (key, value) in self.cache.iter() {
+                    self.store.store(key, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn cache(&self) -> &LruCache<K, V, S> {
+        &self.cache
+    }
+
+    pub fn store(&self) -> &B {
+        &self.store
+    }
+
+    pub fn write_mode(&self) -> WriteMode {
+        self.mode
+    }
+
+    /// Число изменённых элементов, ещё не записанных в хранилище
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Записывает в хранилище элементы, покинувшие кеш; при отложенной записи -
+    /// только изменённые, остальные там уже есть
+    fn demote(&mut self) -> Result<(), B::Error> {
+        let demoted = self.cache.take_demoted();
+        self.store_demoted(demoted)
+    }
+
+    fn store_demoted(&mut self, demoted: Vec<(K, V)>) -> Result<(), B::Error> {
+        for (key, value) in demoted {
+            if matches!(self.mode, WriteMode::Behind { .. }) && !self.dirty.remove(&key) {
+                continue;
+            }
+            self.store.store(&key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Записывает изменённые элементы; при ошибке незаписанные остаются изменёнными
+    fn write_dirty(&mut self) -> Result<(), B::Error> {
+        let keys: Vec<K> = self.dirty.iter().cloned().collect();
+        for key in keys {
+            match self.cache.peek(&key) {
+                Some(value) => {
+                    self.store.store(&key, value)?;
+                    self.dirty.remove(&key);
+                }
+                // просроченный элемент перехватывается и записывается через demote
+                None => {
+                    self.cache.remove(&key);
+                }
+            }
+        }
+        self.demote()?;
+        // остальные ключи без элемента записывать нечем
+        let cache = &self.cache;
+        self.dirty.retain(|key| cache.contains_key(key));
+        Ok(())
+    }
+}
+
+/// Хранилище в дереве sled; ключи и значения кодируются в bincode
+#[cfg(feature = "sled")]
+pub struct SledStore<K, V> {
+    tree: sled::Tree,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+#[cfg(feature = "sled")]
+impl<K, V> SledStore<K, V> {
+    pub fn new(tree: sled::Tree) -> Self {
+        SledStore {
+            tree,
+            marker: PhantomData,
+        }
+    }
+
+    /// Открывает базу sled в каталоге `path` и использует её основное дерево
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let db = sled::open(path).map_err(std::io::Error::from)?;
+        Ok(SledStore::new(sled::Tree::clone(&db)))
+    }
+
+    pub fn tree(&self) -> &sled::Tree {
+        &self.tree
+    }
+}
+
+#[cfg(feature = "sled")]
+impl<K: Serialize, V: Serialize + DeserializeOwned> BackingStore<K, V> for SledStore<K, V> {
+    type Error = SnapshotError;
+
+    fn load(&self, key: &K) -> Result<Option<V>, SnapshotError> {
+        let key = bincode::serialize(key).map_err(SnapshotError::Encoding)?;
+        let Some(value) = self.tree.get(key).map_err(std::io::Error::from)? else {
+            return Ok(None);
+        };
+        bincode::deserialize(&value)
+            .map(Some)
+            .map_err(SnapshotError::Encoding)
+    }
+
+    fn store(&self, key: &K, value: &V) -> Result<(), SnapshotError> {
+        let key = bincode::serialize(key).map_err(SnapshotError::Encoding)?;
+        let value = bincode::serialize(value).map_err(SnapshotError::Encoding)?;
+        self.tree.insert(key, value).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &K) -> Result<(), SnapshotError> {
+        let key = bincode::serialize(key).map_err(SnapshotError::Encoding)?;
+        self.tree.remove(key).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, MockClock};
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+
+    struct MapStore(Mutex<HashMap<u32, String>>);
+
+    impl BackingStore<u32, String> for MapStore {
+        type Error = Infallible;
+
+        fn load(&self, key: &u32) -> Result<Option<String>, Infallible> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn store(&self, key: &u32, value: &String) -> Result<(), Infallible> {
+            self.0.lock().unwrap().insert(*key, value.clone());
+            Ok(())
+        }
+
+        fn delete(&self, key: &u32) -> Result<(), Infallible> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backed_cache() {
+        let store = MapStore(Mutex::new(HashMap::from([(7, "seven".to_string())])));
+        let mut cache = BackedCache::new(LruCache::new(2, CleanupMode::OnAccess), store);
+
+        assert_eq!(cache.get(&7).unwrap().map(String::as_str), Some("seven"));
+        cache.put(1, "one".to_string(), None).unwrap();
+        cache
+            .put(2, "two".to_string(), Some(Duration::ZERO))
+            .unwrap();
+        cache.put(3, "three".to_string(), None).unwrap();
+
+        // "7" вытеснен по ёмкости, "2" истёк - оба записаны в хранилище
+        let stored = cache.store().0.lock().unwrap().clone();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[&2], "two");
+        assert!(!stored.contains_key(&1));
+
+        cache.flush().unwrap();
+        assert_eq!(cache.store().0.lock().unwrap().len(), 4);
+        cache.remove(&7).unwrap();
+        assert_eq!(cache.get(&7).unwrap(), None);
+
+        // истёкшее в памяти значение новее сохранённого и не должно им перекрываться
+        let clock = MockClock::new();
+        let store = MapStore(Mutex::new(HashMap::from([(1, "v1".to_string())])));
+        let mut cache = BackedCache::new(
+            LruCache::builder().capacity(2).clock(clock.clone()).build(),
+            store,
+        );
+        cache
+            .put(1, "v2".to_string(), Some(Duration::from_secs(1)))
+            .unwrap();
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(cache.get(&1).unwrap().map(String::as_str), Some("v2"));
+        assert_eq!(cache.store().0.lock().unwrap()[&1], "v2");
+
+        // удалённый просроченный элемент не записывается обратно
+        cache
+            .put(1, "v3".to_string(), Some(Duration::from_secs(1)))
+            .unwrap();
+        clock.advance(Duration::from_secs(2));
+        cache.remove(&1).unwrap();
+        cache.put(2, "two".to_string(), None).unwrap();
+        cache.flush().unwrap();
+        assert!(!cache.store().0.lock().unwrap().contains_key(&1));
+    }
+
+    #[test]
+    fn test_write_through() {
+        let store = MapStore(Mutex::new(HashMap::new()));
+        let mut cache = BackedCache::with_mode(
+            LruCache::new(1, CleanupMode::OnAccess),
+            store,
+            WriteMode::Through,
+        );
+        cache.put(1, "one".to_string(), None).unwrap();
+        assert_eq!(cache.store().0.lock().unwrap()[&1], "one");
+
+        // вытесненный элемент остаётся в хранилище и загружается обратно
+        cache.put(2, "two".to_string(), None).unwrap();
+        assert_eq!(cache.cache().stats().evictions, 1);
+        assert_eq!(cache.get(&1).unwrap().map(String::as_str), Some("one"));
+
+        cache.remove(&2).unwrap();
+        assert!(!cache.store().0.lock().unwrap().contains_key(&2));
+    }
+
+    #[test]
+    fn test_write_behind() {
+        let store = MapStore(Mutex::new(HashMap::new()));
+        let mut cache = BackedCache::with_mode(
+            LruCache::new(2, CleanupMode::OnAccess),
+            store,
+            WriteMode::Behind { max_dirty: 3 },
+        );
+        cache.put(1, "one".to_string(), None).unwrap();
+        cache.put(2, "two".to_string(), None).unwrap();
+        assert!(cache.store().0.lock().unwrap().is_empty());
+        assert_eq!(cache.dirty_len(), 2);
+
+        cache.flush().unwrap();
+        assert_eq!(cache.dirty_len(), 0);
+        assert_eq!(cache.store().0.lock().unwrap().len(), 2);
+
+        // чистый "1" вытесняется без записи, изменённый "2" - с записью
+        cache.put(2, "TWO".to_string(), None).unwrap();
+        cache.put(3, "three".to_string(), None).unwrap();
+        cache.put(4, "four".to_string(), None).unwrap();
+        assert_eq!(cache.store().0.lock().unwrap()[&2], "TWO");
+        assert_eq!(cache.dirty_len(), 2);
+
+        // превышение max_dirty запускает запись пачкой
+        cache.put(5, "five".to_string(), None).unwrap();
+        cache.put(6, "six".to_string(), None).unwrap();
+        cache.put(7, "seven".to_string(), None).unwrap();
+        cache.put(8, "eight".to_string(), None).unwrap();
+        assert!(cache.dirty_len() <= 3);
+        assert_eq!(cache.store().0.lock().unwrap().len(), 8 - cache.dirty_len());
+
+        // истёкшее изменённое значение записывается раньше, чем загружается сохранённое
+        let clock = MockClock::new();
+        let store = MapStore(Mutex::new(HashMap::from([(1, "v1".to_string())])));
+        let mut cache = BackedCache::with_mode(
+            LruCache::builder().capacity(2).clock(clock.clone()).build(),
+            store,
+            WriteMode::Behind { max_dirty: 8 },
+        );
+        cache
+            .put(1, "v2".to_string(), Some(Duration::from_secs(1)))
+            .unwrap();
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(cache.get(&1).unwrap().map(String::as_str), Some("v2"));
+        assert_eq!(cache.dirty_len(), 0);
+
+        // отключённый кеш не копит изменённые ключи
+        let mut cache = BackedCache::with_mode(
+            LruCache::disabled(),
+            MapStore(Mutex::new(HashMap::new())),
+            WriteMode::Behind { max_dirty: 0 },
+        );
+        cache.put(1, "one".to_string(), None).unwrap();
+        assert_eq!(cache.dirty_len(), 0);
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let store = SledStore::new(db.open_tree("cache").unwrap());
+        store.store(&1u32, &"one".to_string()).unwrap();
+        assert_eq!(store.load(&1).unwrap().as_deref(), Some("one"));
+        store.delete(&1).unwrap();
+        assert_eq!(store.load(&1).unwrap(), None);
+    }
+}
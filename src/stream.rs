@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::{RemovalCause, RemovalListener};
+
+/// Поведение при заполненной очереди потока событий, см. [`removal_stream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Новое событие отбрасывается, число отброшенных возвращает [`RemovalStream::dropped`]
+    DropEvent,
+    /// Операция кеша ждёт, пока обработчик не освободит место
+    ///
+    /// Обработчик не должен выполняться в том же потоке, что и операции кеша,
+    /// иначе ожидание никогда не закончится.
+    Block,
+}
+
+struct State<K, V> {
+    queue: VecDeque<(K, V, RemovalCause)>,
+    waker: Option<Waker>,
+    dropped: u64,
+    sender_alive: bool,
+    receiver_alive: bool,
+}
+
+struct Shared<K, V> {
+    state: Mutex<State<K, V>>,
+    space: Condvar,
+    capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl<K, V> Shared<K, V> {
+    fn lock(&self) -> MutexGuard<'_, State<K, V>> {
+        // очередь остаётся согласованной, даже если другая сторона паниковала
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Передающая сторона, подключается через `LruCacheBuilder::removal_listener`
+pub struct RemovalSender<K, V> {
+    shared: Arc<Shared<K, V>>,
+}
+
+/// Поток событий удаления `(K, V, RemovalCause)`; завершается после удаления кеша
+pub struct RemovalStream<K, V> {
+    shared: Arc<Shared<K, V>>,
+}
+
+/// Очередь событий удаления на `capacity` элементов для асинхронного обработчика
+///
+/// ```ignore
+/// let (tx, mut removals) = removal_stream(1024, Backpressure::DropEvent);
+/// let cache = LruCache::builder().capacity(1000).removal_listener(tx).build();
+/// while let Some((key, value, cause)) = removals.next().await { /* ... */ }
+/// ```
+pub fn removal_stream<K, V>(
+    capacity: usize,
+    backpressure: Backpressure,
+) -> (RemovalSender<K, V>, RemovalStream<K, V>) {
+    assert!(capacity > 0);
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            waker: None,
+            dropped: 0,
+            sender_alive: true,
+            receiver_alive: true,
+        }),
+        space: Condvar::new(),
+        capacity,
+        backpressure,
+    });
+
+    (
+        RemovalSender {
+            shared: shared.clone(),
+        },
+        RemovalStream { shared },
+    )
+}
+
+impl<K, V> RemovalListener<K, V> for RemovalSender<K, V> {
+    fn on_removal(&self, key: K, value: V, cause: RemovalCause) {
+        let shared = &*self.shared;
+        let mut state = shared.lock();
+
+        while state.receiver_alive && state.queue.len() >= shared.capacity {
+            match shared.backpressure {
+                Backpressure::DropEvent => {
+                    state.dropped += 1;
+                    return;
+                }
+                Backpressure::Block => {
+                    state = shared
+                        .space
+                        .wait(state)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+            }
+        }
+        if !state.receiver_alive {
+            return;
+        }
+
+        state.queue.push_back((key, value, cause));
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<K, V> Drop for RemovalSender<K, V> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.sender_alive = false;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<K, V> RemovalStream<K, V> {
+    /// Число событий, отброшенных из-за заполненной очереди в режиме [`Backpressure::DropEvent`]
+    pub fn dropped(&self) -> u64 {
+        self.shared.lock().dropped
+    }
+}
+
+impl<K, V> Stream for RemovalStream<K, V> {
+    type Item = (K, V, RemovalCause);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.shared.lock();
+
+        if let Some(event) = state.queue.pop_front() {
+            self.shared.space.notify_one();
+            return Poll::Ready(Some(event));
+        }
+        if !state.sender_alive {
+            return Poll::Ready(None);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<K, V> Drop for RemovalStream<K, V> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+        // ожидающие места отправители больше не нужны
+        self.shared.space.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LruCache;
+    use futures::StreamExt;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn test_removal_stream() {
+        let (tx, mut removals) = removal_stream(1, Backpressure::DropEvent);
+        let mut cache = LruCache::builder().capacity(1).removal_listener(tx).build();
+
+        cache.put(1, "a", None);
+        cache.put(2, "b", None);
+        cache.put(3, "c", None);
+        assert_eq!(removals.dropped(), 1);
+
+        drop(cache);
+        let events: Vec<_> = block_on(removals.by_ref().collect());
+        assert_eq!(events, vec![(1, "a", RemovalCause::SizeEvicted)]);
+    }
+
+    #[test]
+    fn test_removal_stream_block() {
+        let (tx, removals) = removal_stream(1, Backpressure::Block);
+
+        let producer = thread::spawn(move || {
+            let mut cache = LruCache::builder().capacity(1).removal_listener(tx).build();
+            for i in 0..10 {
+                cache.put(i, i * 10, None);
+            }
+        });
+
+        let events: Vec<_> = block_on(removals.map(|(key, _, _)| key).collect());
+        producer.join().unwrap();
+        assert_eq!(events, (0..9).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,318 @@
+use std::borrow::Borrow;
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use crate::buffer::BufferedCache;
+use crate::flight::InFlight;
+use crate::{CacheStats, Cleaner, CleanupMode, DefaultHashBuilder, LruCache, Ttl};
+
+const POISONED: &str = "кеш отравлен паникой в другом потоке";
+
+/// Потокобезопасный кеш: [`LruCache`] под общим `RwLock` с методами на `&self`
+///
+/// Копии (`clone`) указывают на один и тот же кеш, поэтому его можно передавать
+/// в потоки без внешнего `Arc<Mutex<..>>`. Чтение возвращает копию значения;
+/// остальные методы `LruCache` доступны через [`SyncLruCache::lock`].
+///
+/// `get` и `with_value` берут только разделяемую блокировку и не ждут друг друга:
+/// прочитанный элемент запоминается в буфере, а в начало очереди LRU переносится
+/// при следующей эксклюзивной блокировке. При переполнении буфера чтения теряются,
+/// поэтому под высокой нагрузкой порядок вытеснения приблизителен.
+pub struct SyncLruCache<K, V, S = DefaultHashBuilder> {
+    inner: Arc<BufferedCache<K, V, S>>,
+    loading: Arc<InFlight<K, V>>,
+}
+
+/// [`SyncLruCache`] со значениями в `Arc`: `get` копирует только указатель, и значение можно
+/// держать после снятия блокировки, даже если элемент вытеснят
+pub type ArcLruCache<K, V, S = DefaultHashBuilder> = SyncLruCache<K, Arc<V>, S>;
+
+impl<K, V, S> Clone for SyncLruCache<K, V, S> {
+    fn clone(&self) -> Self {
+        SyncLruCache {
+            inner: self.inner.clone(),
+            loading: self.loading.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> SyncLruCache<K, V> {
+    pub fn new(capacity: usize, cleanup_mode: CleanupMode) -> Self {
+        LruCache::new(capacity, cleanup_mode).into()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> From<LruCache<K, V, S>> for SyncLruCache<K, V, S> {
+    fn from(cache: LruCache<K, V, S>) -> Self {
+        SyncLruCache {
+            inner: Arc::new(BufferedCache::new(cache)),
+            loading: Arc::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> SyncLruCache<K, V, S> {
+    /// Блокирует кеш для нескольких операций подряд или методов, которых нет у обёртки
+    ///
+    /// Паника внутри операции (например, в слушателе) может оставить кеш
+    /// несогласованным, поэтому отравленная блокировка приводит к панике.
+    pub fn lock(&self) -> RwLockWriteGuard<'_, LruCache<K, V, S>> {
+        self.inner.write().expect(POISONED)
+    }
+
+    fn shared(&self) -> RwLockReadGuard<'_, LruCache<K, V, S>> {
+        self.inner.shared().expect(POISONED)
+    }
+
+    pub fn put(&self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        self.lock().put(key, value, ttl)
+    }
+
+    pub fn push(&self, key: K, value: V, ttl: Option<Duration>) -> Option<(K, V)> {
+        self.lock().push(key, value, ttl)
+    }
+
+    /// Копия значения с отметкой элемента как недавно использованного
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.with_value(key, V::clone)
+    }
+
+    /// Чтение без копирования: `f` выполняется под разделяемой блокировкой
+    pub fn with_value<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.inner.read(key, f).expect(POISONED)
+    }
+
+    /// Копия значения без изменения порядка LRU
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shared().peek(key).cloned()
+    }
+
+    /// Возвращает элемент, а при его отсутствии вычисляет и сохраняет
+    ///
+    /// `f` выполняется под блокировкой, поэтому не должно обращаться к этому же кешу.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&self, key: K, f: F) -> V
+    where
+        V: Clone,
+    {
+        if let Some(value) = self.inner.read_hit(&key, V::clone).expect(POISONED) {
+            return value;
+        }
+        self.lock().get_or_insert_with(key, f).clone()
+    }
+
+    /// Возвращает элемент, а при его отсутствии загружает через `f` без блокировки кеша
+    ///
+    /// Одновременные промахи по одному ключу выполняют `f` один раз: остальные потоки
+    /// ждут и получают загруженное значение, а если `f` запаниковало - загружают сами.
+    /// `f` не должно запрашивать тот же ключ.
+    pub fn get_with<F: FnOnce() -> V>(&self, key: K, f: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+    {
+        match self.try_get_with(key, || Ok::<_, Infallible>(f())) {
+            Ok(value) => value,
+        }
+    }
+
+    /// Вариант `get_with` для загрузки с ошибкой: ошибка возвращается загрузившему
+    /// потоку, а ожидавшие потоки повторяют загрузку сами
+    pub fn try_get_with<F, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+        K: Clone,
+        V: Clone,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        self.loading.try_run(&key, || {
+            // предыдущая загрузка могла завершиться между промахом и регистрацией
+            if let Some(value) = self.peek(&key) {
+                return Ok(value);
+            }
+            let value = f()?;
+            self.put(key.clone(), value.clone(), None);
+            Ok(value)
+        })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared().contains_key(key)
+    }
+
+    pub fn ttl<Q>(&self, key: &Q) -> Option<Ttl>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared().ttl(key)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().remove(key)
+    }
+
+    pub fn pop_lru(&self) -> Option<(K, V)> {
+        self.lock().pop_lru()
+    }
+
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&self, f: F) {
+        self.lock().retain(f)
+    }
+
+    pub fn evict_expired(&self) {
+        self.lock().evict_expired()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared().is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared().capacity()
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.lock().set_capacity(capacity)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> SyncLruCache<K, Arc<V>, S> {
+    /// Добавляет значение, упаковывая его в `Arc`
+    pub fn put_value(&self, key: K, value: V, ttl: Option<Duration>) -> Option<Arc<V>> {
+        self.put(key, Arc::new(value), ttl)
+    }
+}
+
+impl<K, V, S> SyncLruCache<K, V, S>
+where
+    K: Eq + Hash + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher + Send + Sync + 'static,
+{
+    /// Фоновый поток очистки, см. [`LruCache::spawn_cleaner`]
+    pub fn spawn_cleaner(&self, interval: Duration) -> Cleaner {
+        let cache = Arc::downgrade(&self.inner);
+        Cleaner::spawn(interval, move || {
+            let Some(cache) = cache.upgrade() else {
+                return false;
+            };
+            let Ok(mut cache) = cache.write() else {
+                return false;
+            };
+            cache.run_maintenance();
+            true
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_sync_cache() {
+        let cache = SyncLruCache::new(100, CleanupMode::OnAccess);
+
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..25 {
+                        cache.put(t * 25 + i, format!("{t}-{i}"), None);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.get(&26), Some("1-1".to_string()));
+        assert_eq!(cache.with_value(&26, String::len), Some(3));
+        assert_eq!(cache.get_or_insert_with(200, || "new".into()), "new");
+        assert_eq!(cache.lock().peek(&200).map(String::as_str), Some("new"));
+    }
+
+    #[test]
+    fn test_get_with() {
+        static LOADS: AtomicU32 = AtomicU32::new(0);
+        let cache = SyncLruCache::new(10, CleanupMode::OnAccess);
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    cache.get_with("a", || {
+                        LOADS.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(50));
+                        1
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 1);
+        }
+
+        assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.get_with("a", || 2), 1);
+    }
+
+    #[test]
+    fn test_arc_values() {
+        let cache: ArcLruCache<&str, Vec<u8>> = SyncLruCache::new(1, CleanupMode::OnAccess);
+        cache.put_value("a", vec![0; 1024], None);
+
+        let first = cache.get(&"a").unwrap();
+        let second = cache.get(&"a").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // вытесненное значение остаётся у читателей
+        cache.put_value("b", vec![1], None);
+        assert!(!cache.contains_key(&"a"));
+        assert_eq!(first.len(), 1024);
+        assert_eq!(Arc::strong_count(&first), 2);
+    }
+}
@@ -0,0 +1,154 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::time::Duration;
+
+use crate::{KeyWrapper, LruCache, RemovalCause};
+
+/// Теги элементов и обратный индекс тег -> ключи, см. [`LruCache::put_tagged`]
+///
+/// Ключ удаляется из индекса вместе с элементом, поэтому индекс не растёт
+/// за счёт давно вытесненных элементов.
+#[derive(Clone)]
+pub(crate) struct TagIndex<K> {
+    by_tag: HashMap<String, HashSet<K>>,
+    by_key: HashMap<K, Vec<String>>,
+}
+
+impl<K> TagIndex<K> {
+    pub(crate) fn new() -> Self {
+        TagIndex {
+            by_tag: HashMap::new(),
+            by_key: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.by_tag.clear();
+        self.by_key.clear();
+    }
+}
+
+impl<K: Eq + Hash> TagIndex<K> {
+    pub(crate) fn remove<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.by_key.is_empty() {
+            return;
+        }
+        let Some(tags) = self.by_key.remove(key) else {
+            return;
+        };
+        for tag in tags {
+            if let Some(keys) = self.by_tag.get_mut(&tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.by_tag.remove(&tag);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, key: K, tags: Vec<String>)
+    where
+        K: Clone,
+    {
+        self.remove(&key);
+        if tags.is_empty() {
+            return;
+        }
+        for tag in &tags {
+            self.by_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        self.by_key.insert(key, tags);
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> LruCache<K, V, S> {
+    /// Вставка с тегами (например, `user:42`, `report`) для группового удаления через
+    /// [`LruCache::invalidate_tag`]
+    ///
+    /// Теги заменяют прежние теги ключа; перезапись через [`LruCache::put`] их сохраняет.
+    pub fn put_tagged<I>(&mut self, key: K, value: V, ttl: Option<Duration>, tags: I) -> Option<V>
+    where
+        K: Clone,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let old = self.put(key.clone(), value, ttl);
+        // отключённый кеш (ёмкость 0) элемент не сохраняет
+        if self.map.contains_key(KeyWrapper::from_ref(&key)) {
+            let mut tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+            tags.sort();
+            tags.dedup();
+            self.tags.insert(key, tags);
+        }
+        old
+    }
+
+    /// Удаляет все элементы с тегом `tag`; возвращает число удалённых непросроченных
+    ///
+    /// Слушатель получает удалённые элементы с причиной [`RemovalCause::Explicit`].
+    pub fn invalidate_tag(&mut self, tag: &str) -> usize {
+        let Some(keys) = self.tags.by_tag.remove(tag) else {
+            return 0;
+        };
+        let now = self.now();
+        let mut removed = 0;
+        for key in keys {
+            let Some(&node_ptr) = self.map.get(KeyWrapper::from_ref(&key)) else {
+                continue;
+            };
+            let node = self.unlink(node_ptr);
+            let cause = if node.expired_at(now) {
+                RemovalCause::Expired
+            } else {
+                removed += 1;
+                RemovalCause::Explicit
+            };
+            self.notify(node.key, node.value, cause);
+        }
+        removed
+    }
+
+    /// Теги элемента в порядке сортировки; пустой срез, если тегов или элемента нет
+    pub fn tags<Q>(&self, key: &Q) -> &[String]
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.tags.by_key.get(key).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_invalidate_tag() {
+        let mut cache = LruCache::new(3, CleanupMode::OnAccess);
+        cache.put_tagged("profile", 1, None, ["user:42"]);
+        cache.put_tagged("orders", 2, None, ["user:42", "report"]);
+        cache.put_tagged("summary", 3, None, ["report", "report"]);
+        assert_eq!(cache.tags(&"summary"), ["report"]);
+
+        assert_eq!(cache.invalidate_tag("user:42"), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.tags(&"orders"), [] as [String; 0]);
+        assert_eq!(cache.invalidate_tag("user:42"), 0);
+
+        // вытесненный элемент покидает индекс
+        cache.put("a", 4, None);
+        cache.put("b", 5, None);
+        cache.put("c", 6, None);
+        assert_eq!(cache.invalidate_tag("report"), 0);
+        assert!(cache.tags.by_tag.is_empty() && cache.tags.by_key.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use std::hash::{BuildHasher, Hash};
+use std::time::Duration;
+
+use crate::LruCache;
+
+/// Уровень многоуровневого кеша, см. [`TieredCache`]
+///
+/// Время жизни передаётся как оставшееся, `None` означает отсутствие ограничения.
+/// Ошибки удалённого уровня реализация обрабатывает сама: для кеша сбой равносилен промаху.
+pub trait CacheTier<K, V> {
+    /// Значение с оставшимся временем жизни
+    fn get(&mut self, key: &K) -> Option<(V, Option<Duration>)>;
+
+    /// Вставка; возвращает элементы, вытесненные ради освобождения места
+    fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Vec<(K, V, Option<Duration>)>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+}
+
+/// `LruCache` как уровень: вытесненные при вставке элементы возвращаются вызывающему,
+/// а не слушателю удалений
+impl<K: Eq + Hash, V: Clone, S: BuildHasher> CacheTier<K, V> for LruCache<K, V, S> {
+    fn get(&mut self, key: &K) -> Option<(V, Option<Duration>)> {
+        let value = LruCache::get(self, key)?.clone();
+        let ttl = match self.ttl(key) {
+            Some(crate::Ttl::Remaining(ttl)) => Some(ttl),
+            _ => None,
+        };
+        Some((value, ttl))
+    }
+
+    fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Vec<(K, V, Option<Duration>)> {
+        let captured = self.demoted.is_some();
+        self.capture_evictions();
+        LruCache::put(self, key, value, Some(ttl.unwrap_or(Duration::MAX)));
+        let evicted = self.take_evicted();
+        if !captured {
+            self.demoted = None;
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        LruCache::remove(self, key)
+    }
+}
+
+/// Двухуровневый кеш: чтение проверяет `L1`, затем `L2`; найденное в `L2` переносится
+/// в `L1`, а вытесненное из `L1` опускается в `L2`
+///
+/// Элемент хранится только на одном уровне. Сам `TieredCache` тоже реализует [`CacheTier`],
+/// поэтому уровни можно вкладывать. Для вытеснения на диск с обработкой ошибок
+/// ввода-вывода есть `SpilloverCache` (опция `snapshot`).
+pub struct TieredCache<L1, L2> {
+    l1: L1,
+    l2: L2,
+}
+
+impl<L1, L2> TieredCache<L1, L2> {
+    pub fn new(l1: L1, l2: L2) -> Self {
+        TieredCache { l1, l2 }
+    }
+
+    pub fn l1(&self) -> &L1 {
+        &self.l1
+    }
+
+    pub fn l2(&self) -> &L2 {
+        &self.l2
+    }
+
+    pub fn into_inner(self) -> (L1, L2) {
+        (self.l1, self.l2)
+    }
+
+    /// Значение из `L1` или `L2` (с переносом в `L1`)
+    pub fn get<K, V>(&mut self, key: &K) -> Option<V>
+    where
+        Self: CacheTier<K, V>,
+    {
+        CacheTier::get(self, key).map(|(value, _)| value)
+    }
+
+    /// Вставка в `L1`; прежняя копия в `L2` удаляется
+    pub fn put<K, V>(&mut self, key: K, value: V, ttl: Option<Duration>)
+    where
+        Self: CacheTier<K, V>,
+    {
+        CacheTier::put(self, key, value, ttl);
+    }
+
+    /// Удаление с обоих уровней
+    pub fn remove<K, V>(&mut self, key: &K) -> Option<V>
+    where
+        Self: CacheTier<K, V>,
+    {
+        CacheTier::remove(self, key)
+    }
+
+    /// Вставка в `L1` с переносом вытесненного в `L2`; возвращает вытесненное из `L2`
+    fn put_l1<K, V>(
+        &mut self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Vec<(K, V, Option<Duration>)>
+    where
+        L1: CacheTier<K, V>,
+        L2: CacheTier<K, V>,
+    {
+        let mut evicted = Vec::new();
+        for (key, value, ttl) in self.l1.put(key, value, ttl) {
+            evicted.extend(self.l2.put(key, value, ttl));
+        }
+        evicted
+    }
+}
+
+impl<K, V, L1, L2> CacheTier<K, V> for TieredCache<L1, L2>
+where
+    K: Clone,
+    V: Clone,
+    L1: CacheTier<K, V>,
+    L2: CacheTier<K, V>,
+{
+    fn get(&mut self, key: &K) -> Option<(V, Option<Duration>)> {
+        if let Some(entry) = self.l1.get(key) {
+            return Some(entry);
+        }
+
+        let (value, ttl) = self.l2.get(key)?;
+        self.l2.remove(key);
+        // вытесненное из `L2` при переносе некуда опустить
+        self.put_l1(key.clone(), value.clone(), ttl);
+        Some((value, ttl))
+    }
+
+    fn put(&mut self, key: K, value: V, ttl: Option<Duration>) -> Vec<(K, V, Option<Duration>)> {
+        self.l2.remove(&key);
+        self.put_l1(key, value, ttl)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let l1 = self.l1.remove(key);
+        let l2 = self.l2.remove(key);
+        l1.or(l2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanupMode, Ttl};
+
+    #[test]
+    fn test_tiered_cache() {
+        let mut cache = TieredCache::new(
+            LruCache::new(2, CleanupMode::OnAccess),
+            LruCache::new(10, CleanupMode::OnAccess),
+        );
+        cache.put(1, "one", None);
+        cache.put(2, "two", Some(Duration::from_secs(60)));
+        cache.put(3, "three", None);
+        assert!(!cache.l1().contains_key(&1));
+        assert!(cache.l2().contains_key(&1));
+
+        // попадание в L2 переносит элемент в L1 и опускает туда давно использованный "2"
+        assert_eq!(cache.get(&1), Some("one"));
+        assert!(cache.l1().contains_key(&1) && !cache.l2().contains_key(&1));
+        assert!(matches!(cache.l2().ttl(&2), Some(Ttl::Remaining(_))));
+        assert_eq!(cache.l1().stats().evictions, 2);
+
+        assert_eq!(cache.remove(&2), Some("two"));
+        assert_eq!(cache.get(&2), None);
+    }
+}
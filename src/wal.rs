@@ -0,0 +1,361 @@
+use std::borrow::Borrow;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, Hash};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{DefaultHashBuilder, KeyWrapper, LruCache, SnapshotError};
+
+const MAGIC: &[u8; 4] = b"LRUW";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Порог сжатия журнала по умолчанию, см. [`WalLruCache::set_compact_threshold`]
+const DEFAULT_COMPACT_THRESHOLD: u64 = 16 << 20;
+
+/// Запись журнала; `written_at` - момент записи (наносекунды Unix), от которого
+/// при восстановлении отсчитывается `ttl`
+#[derive(Serialize, Deserialize)]
+enum Record<K, V> {
+    Put {
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+        written_at: u64,
+    },
+    Remove(K),
+    Clear,
+}
+
+/// Кеш с журналом упреждающей записи: `put`, `remove` и `clear` дописывают запись
+/// в файл до изменения кеша, а [`WalLruCache::open`] восстанавливает кеш из журнала
+///
+/// Каждая запись сразу передаётся ОС, поэтому падение процесса ничего не теряет;
+/// от сбоя системы защищает только [`WalLruCache::sync`]. Чтения в журнал не попадают,
+/// порядок LRU после восстановления определяется порядком записей. Недописанная
+/// при сбое последняя запись отбрасывается. Когда журнал вырастает больше порога
+/// (и вдвое больше, чем после прошлого сжатия), он переписывается текущим содержимым.
+pub struct WalLruCache<K, V, S = DefaultHashBuilder> {
+    cache: LruCache<K, V, S>,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    compacted_size: u64,
+    compact_threshold: u64,
+}
+
+impl<K, V, S> WalLruCache<K, V, S>
+where
+    K: Eq + Hash + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    S: BuildHasher,
+{
+    /// Открывает журнал `path` (создавая его при отсутствии) и воспроизводит его в `cache`
+    ///
+    /// Параметры кеша (ёмкость, TTL по умолчанию, слушатели) задаются переданным кешем,
+    /// обычно пустым: элементы журнала добавляются к его содержимому.
+    pub fn open(
+        path: impl AsRef<Path>,
+        mut cache: LruCache<K, V, S>,
+    ) -> Result<Self, SnapshotError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let data = fs::read(&path)?;
+        let size = if data.is_empty() {
+            file.write_all(MAGIC)?;
+            file.write_all(&VERSION.to_le_bytes())?;
+            HEADER_LEN as u64
+        } else {
+            if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+                return Err(SnapshotError::NotSnapshot);
+            }
+            let version = u16::from_le_bytes([data[4], data[5]]);
+            if version != VERSION {
+                return Err(SnapshotError::UnsupportedVersion(version));
+            }
+
+            let valid = replay(&data[HEADER_LEN..], &mut cache)?;
+            let size = (HEADER_LEN + valid) as u64;
+            if size < data.len() as u64 {
+                // хвост, недописанный при сбое; дозапись продолжится с новой границы
+                file.set_len(size)?;
+            }
+            size
+        };
+
+        Ok(WalLruCache {
+            cache,
+            path,
+            file,
+            size,
+            // объём живых записей неизвестен до первого сжатия
+            compacted_size: HEADER_LEN as u64,
+            compact_threshold: DEFAULT_COMPACT_THRESHOLD,
+        })
+    }
+
+    /// Добавление элемента с записью в журнал, см. [`LruCache::put`]
+    ///
+    /// В журнал попадает срок, вычисленный кешем (с TTL по умолчанию и [`Expiry`](crate::Expiry)),
+    /// поэтому после восстановления элемент истекает в тот же момент.
+    pub fn put(
+        &mut self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<Option<V>, SnapshotError> {
+        let now = self.cache.now();
+        let expires_at = self.cache.expires_at_for(&key, &value, ttl, now);
+        let ttl = expires_at.map_or(Duration::MAX, |expires_at| {
+            expires_at.saturating_duration_since(now)
+        });
+        self.append(&Record::Put {
+            key: &key,
+            value: &value,
+            ttl: Some(ttl),
+            written_at: unix_now(),
+        })?;
+        Ok(self.cache.put_returning(key, value, expires_at, now))
+    }
+
+    /// Удаление элемента с записью в журнал
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, SnapshotError> {
+        // просроченный, но ещё не удалённый элемент тоже удаляется из журнала
+        if !self.cache.map.contains_key(KeyWrapper::from_ref(key)) {
+            return Ok(None);
+        }
+        self.append(&Record::<&K, &V>::Remove(key))?;
+        Ok(self.cache.remove(key))
+    }
+
+    /// Опустошение кеша с записью в журнал
+    pub fn clear(&mut self) -> Result<(), SnapshotError> {
+        self.append(&Record::<&K, &V>::Clear)?;
+        self.cache.clear();
+        Ok(())
+    }
+
+    /// Переписывает журнал текущим содержимым кеша через временный файл
+    /// с атомарным переименованием
+    pub fn compact(&mut self) -> Result<(), SnapshotError> {
+        let written_at = unix_now();
+        let mut data = Vec::with_capacity(HEADER_LEN);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        // от давно использованных к недавним, чтобы восстановить порядок LRU
+        for (key, value, ttl) in self.cache.snapshot_entries().into_iter().rev() {
+            let ttl = Some(ttl.unwrap_or(Duration::MAX));
+            encode(
+                &mut data,
+                &Record::Put {
+                    key,
+                    value,
+                    ttl,
+                    written_at,
+                },
+            )?;
+        }
+
+        let mut file_name = self.path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let temp = self.path.with_file_name(file_name);
+        let written = File::create(&temp).and_then(|mut file| {
+            file.write_all(&data)?;
+            file.sync_all()
+        });
+        if let Err(error) = written.and_then(|()| fs::rename(&temp, &self.path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(error.into());
+        }
+
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.size = data.len() as u64;
+        self.compacted_size = self.size;
+        Ok(())
+    }
+
+    /// Дописывает запись, до изменения кеша; разросшийся журнал сначала сжимается,
+    /// пока содержимое кеша ещё соответствует уже записанному
+    fn append(&mut self, record: &Record<&K, &V>) -> Result<(), SnapshotError> {
+        if self.size > self.compact_threshold.max(self.compacted_size * 2) {
+            self.compact()?;
+        }
+
+        let mut data = Vec::new();
+        encode(&mut data, record)?;
+        self.file.write_all(&data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> WalLruCache<K, V, S> {
+    /// Чтение без записи в журнал, см. [`LruCache::get`]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(key)
+    }
+
+    /// Кеш для чтения; изменения в обход журнала не сохраняются
+    pub fn cache(&self) -> &LruCache<K, V, S> {
+        &self.cache
+    }
+
+    /// Сбрасывает журнал на диск (`fsync`)
+    pub fn sync(&self) -> Result<(), SnapshotError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Размер журнала в байтах
+    pub fn log_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Размер журнала, после которого он сжимается, по умолчанию 16 МиБ
+    pub fn set_compact_threshold(&mut self, bytes: u64) {
+        self.compact_threshold = bytes;
+    }
+}
+
+/// Воспроизводит записи в кеш; возвращает длину целых записей в начале `data`
+fn replay<K, V, S>(data: &[u8], cache: &mut LruCache<K, V, S>) -> Result<usize, SnapshotError>
+where
+    K: Eq + Hash + DeserializeOwned,
+    V: DeserializeOwned,
+    S: BuildHasher,
+{
+    let now = unix_now();
+    let mut offset = 0;
+    while let Some(frame) = data.get(offset..offset + 8) {
+        let len = u32::from_le_bytes(frame[..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(frame[4..].try_into().unwrap());
+        let Some(payload) = data.get(offset + 8..offset + 8 + len) else {
+            break;
+        };
+        if crc32fast::hash(payload) != checksum {
+            break;
+        }
+
+        match bincode::deserialize(payload).map_err(SnapshotError::Encoding)? {
+            Record::Put {
+                key,
+                value,
+                ttl,
+                written_at,
+            } => {
+                let elapsed = Duration::from_nanos(now.saturating_sub(written_at));
+                match ttl {
+                    Some(ttl) if ttl != Duration::MAX && ttl <= elapsed => {
+                        cache.remove(&key);
+                    }
+                    Some(ttl) if ttl != Duration::MAX => {
+                        cache.put(key, value, Some(ttl - elapsed));
+                    }
+                    ttl => {
+                        cache.put(key, value, ttl);
+                    }
+                }
+            }
+            Record::Remove(key) => {
+                cache.remove(&key);
+            }
+            Record::Clear => cache.clear(),
+        }
+        offset += 8 + len;
+    }
+    Ok(offset)
+}
+
+/// Кадр записи: длина и CRC-32 данных, затем сами данные в bincode
+fn encode<T: Serialize>(out: &mut Vec<u8>, record: &T) -> Result<(), SnapshotError> {
+    let payload = bincode::serialize(record).map_err(SnapshotError::Encoding)?;
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    out.extend_from_slice(&payload);
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanupMode;
+
+    #[test]
+    fn test_wal_replay_and_compaction() {
+        let path = std::env::temp_dir().join(format!("lru-wal-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let new_cache = || LruCache::new(3, CleanupMode::OnAccess);
+
+        let mut cache = WalLruCache::open(&path, new_cache()).unwrap();
+        cache.put(1u32, "one".to_string(), None).unwrap();
+        cache
+            .put(2, "two".to_string(), Some(Duration::from_secs(60)))
+            .unwrap();
+        cache.put(3, "three".to_string(), None).unwrap();
+        cache.remove(&1).unwrap();
+        cache.put(4, "four".to_string(), None).unwrap();
+        drop(cache);
+
+        // недописанная запись в конце журнала отбрасывается
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[42, 0, 0, 0, 1]).unwrap();
+        drop(file);
+
+        let mut cache = WalLruCache::open(&path, new_cache()).unwrap();
+        let keys: Vec<_> = cache.cache().keys().copied().collect();
+        assert_eq!(keys, vec![4, 3, 2]);
+        assert!(matches!(
+            cache.cache().ttl(&2),
+            Some(crate::Ttl::Remaining(_))
+        ));
+
+        for i in 0..10 {
+            cache.put(4, i.to_string(), None).unwrap();
+        }
+        let size = cache.log_size();
+        cache.set_compact_threshold(0);
+        cache.put(5, "five".to_string(), None).unwrap();
+        assert!(cache.log_size() < size);
+        drop(cache);
+
+        let cache = WalLruCache::open(&path, new_cache()).unwrap();
+        let keys: Vec<_> = cache.cache().keys().copied().collect();
+        assert_eq!(keys, vec![5, 4, 3]);
+        drop(cache);
+
+        // TTL по умолчанию сохраняется в журнале и не начинается заново при восстановлении
+        let mut short = new_cache();
+        short.set_default_ttl(Some(Duration::from_secs(60)));
+        let mut cache = WalLruCache::open(&path, short).unwrap();
+        cache.put(6, "six".to_string(), None).unwrap();
+        drop(cache);
+        let mut long = new_cache();
+        long.set_default_ttl(Some(Duration::from_secs(3600)));
+        let cache = WalLruCache::open(&path, long).unwrap();
+        assert!(matches!(
+            cache.cache().ttl(&6),
+            Some(crate::Ttl::Remaining(ttl)) if ttl <= Duration::from_secs(60)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+/// Вес элемента для кеша с ёмкостью, заданной суммарным весом
+///
+/// Вес вычисляется при вставке и перезаписи элемента; изменения значения
+/// через `get_mut` и аналоги на вес не влияют.
+pub trait Weigher<K, V> {
+    fn weight(&self, key: &K, value: &V) -> u32;
+}
+
+impl<K, V, F> Weigher<K, V> for F
+where
+    F: Fn(&K, &V) -> u32,
+{
+    fn weight(&self, key: &K, value: &V) -> u32 {
+        self(key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CleanupMode, LruCache};
+
+    #[test]
+    fn test_weight_eviction() {
+        let mut cache =
+            LruCache::with_weigher(10, CleanupMode::OnDemand, |_: &&str, v: &Vec<u8>| {
+                v.len() as u32
+            });
+        cache.put("a", vec![0; 4], None);
+        cache.put("b", vec![0; 4], None);
+        assert_eq!(cache.total_weight(), 8);
+
+        // для 6 единиц веса приходится вытеснить оба элемента
+        assert_eq!(cache.push("c", vec![0; 6], None), Some(("a", vec![0; 4])));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"c", &"b"]);
+        assert_eq!(cache.total_weight(), 10);
+
+        cache.put("d", vec![0; 1], None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"d", &"c"]);
+        assert_eq!(cache.total_weight(), 7);
+    }
+
+    #[test]
+    fn test_weight_changes_on_overwrite() {
+        let mut cache =
+            LruCache::with_weigher(10, CleanupMode::OnDemand, |_: &&str, v: &Vec<u8>| {
+                v.len() as u32
+            });
+        cache.put("a", vec![0; 3], None);
+        cache.put("b", vec![0; 3], None);
+
+        cache.put("b", vec![0; 1], None);
+        assert_eq!(cache.total_weight(), 4);
+
+        cache.put("a", vec![0; 10], None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a"]);
+        assert_eq!(cache.total_weight(), 10);
+
+        assert_eq!(cache.remove("a"), Some(vec![0; 10]));
+        assert_eq!(cache.total_weight(), 0);
+    }
+
+    #[test]
+    fn test_oversized_entry() {
+        let mut cache =
+            LruCache::with_weigher(10, CleanupMode::OnDemand, |_: &&str, v: &Vec<u8>| {
+                v.len() as u32
+            });
+        cache.put("a", vec![0; 2], None);
+
+        cache.entry("b").or_insert(vec![0; 20]);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"b"]);
+        assert_eq!(cache.total_weight(), 20);
+
+        cache.put("c", vec![0; 2], None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"c"]);
+        assert_eq!(cache.total_weight(), 2);
+
+        cache.set_capacity(1);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_dual_limits() {
+        let mut cache =
+            LruCache::with_limits(3, 100, CleanupMode::OnDemand, |_: &u32, v: &Vec<u8>| {
+                v.len() as u32
+            });
+
+        // много мелких элементов упираются в ограничение на число
+        for i in 0..5 {
+            cache.put(i, vec![0; 1], None);
+        }
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&4, &3, &2]);
+        assert_eq!(cache.total_weight(), 3);
+
+        // крупный элемент упирается в ограничение на вес
+        cache.put(10, vec![0; 99], None);
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&10, &4]);
+        assert_eq!(cache.total_weight(), 100);
+
+        cache.set_max_entries(Some(1));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&10]);
+        cache.set_max_entries(Some(0));
+        assert!(cache.is_disabled() && cache.is_empty());
+        cache.put(11, vec![0; 1], None);
+        assert!(cache.is_empty());
+        cache.set_max_entries(None);
+        assert_eq!(cache.max_entries(), None);
+
+        let mut cache = LruCache::with_limits(0, 100, CleanupMode::OnDemand, |_: &u32, _: &u32| 1);
+        cache.put(1, 1, None);
+        assert!(cache.is_disabled() && cache.is_empty());
+    }
+}